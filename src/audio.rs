@@ -1,44 +1,654 @@
 // Audio capture and processing module
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, Host, Stream, StreamConfig, SupportedStreamConfig};
+use cpal::{
+    Device, FromSample, Host, HostId, Sample, SampleFormat, SizedSample, Stream, StreamConfig, SupportedBufferSize,
+    SupportedStreamConfig, SupportedStreamConfigRange,
+};
 use log::{debug, error, info, warn};
 use ringbuf::{traits::*, HeapRb};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-/// Default ring buffer capacity (8192 samples = ~185ms at 44.1kHz)
+use crate::playback::PlaybackControl;
+use crate::resample::Resampler;
+
+/// Default ring buffer capacity (8192 frames = ~185ms at 44.1kHz)
 pub const RING_BUFFER_CAPACITY: usize = 8192;
 
-/// Type alias for the ring buffer producer (thread-safe)
-pub type RingProducer = Arc<Mutex<ringbuf::HeapProd<f32>>>;
+/// Number of interleaved channels carried through the ring buffer (left, right)
+pub const RING_CHANNELS: usize = 2;
+
+/// -3 dB, the downmix gain for channels shared by both sides
+const SHARED_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// (left, right) downmix gains per channel for the standard WAVE/SMPTE layouts
+/// Centre, LFE and back-centre feed both sides at -3 dB; surrounds stay on their side
+fn downmix_gains(channels: usize) -> Option<&'static [(f32, f32)]> {
+    const L: (f32, f32) = (1.0, 0.0);
+    const R: (f32, f32) = (0.0, 1.0);
+    const BOTH: (f32, f32) = (SHARED_GAIN, SHARED_GAIN);
+    match channels {
+        // L R C
+        3 => Some(&[L, R, BOTH]),
+        // L R Ls Rs
+        4 => Some(&[L, R, L, R]),
+        // L R C Ls Rs
+        5 => Some(&[L, R, BOTH, L, R]),
+        // 5.1: L R C LFE Ls Rs
+        6 => Some(&[L, R, BOTH, BOTH, L, R]),
+        // 6.1: L R C LFE Cs Ls Rs
+        7 => Some(&[L, R, BOTH, BOTH, BOTH, L, R]),
+        // 7.1: L R C LFE Lb Rb Ls Rs
+        8 => Some(&[L, R, BOTH, BOTH, L, R, L, R]),
+        _ => None,
+    }
+}
 
 /// Type alias for the ring buffer consumer
 pub type RingConsumer = ringbuf::HeapCons<f32>;
 
-/// Create a new ring buffer for audio samples
+/// Create a new ring buffer for interleaved stereo audio frames
 pub fn create_ring_buffer() -> (RingProducer, RingConsumer) {
-    let ring_buffer = HeapRb::<f32>::new(RING_BUFFER_CAPACITY);
+    let ring_buffer = HeapRb::<f32>::new(RING_BUFFER_CAPACITY * RING_CHANNELS);
     let (producer, consumer) = ring_buffer.split();
     
-    info!("Created ring buffer with capacity: {} samples (~{:.1}ms at 44.1kHz)", 
+    info!("Created ring buffer with capacity: {} stereo frames (~{:.1}ms at 44.1kHz)", 
           RING_BUFFER_CAPACITY, 
           (RING_BUFFER_CAPACITY as f32 / 44100.0) * 1000.0);
     
-    (Arc::new(Mutex::new(producer)), consumer)
+    (RingProducer::new(producer), consumer)
+}
+
+/// Producers owned by whichever source is currently writing
+struct WriterParts {
+    ring: ringbuf::HeapProd<f32>,
+    /// Optional second ring receiving a copy of every frame (used for recording)
+    tee: Option<ringbuf::HeapProd<f32>>,
+}
+
+/// Shareable handle to the write side of the ring buffer
+///
+/// The running source takes the producer out as a `SampleWriter` and owns it
+/// exclusively, so writes need no lock. Dropping the writer puts the producer
+/// back, letting the next source reuse the same ring buffer.
+#[derive(Clone)]
+pub struct RingProducer {
+    slot: Arc<Mutex<Option<WriterParts>>>,
+    overruns: Arc<AtomicU64>,
+    tee_overruns: Arc<AtomicU64>,
+    /// Sample rate of the frames currently in the ring buffer
+    sample_rate: Arc<AtomicU32>,
+    /// Fixed rate every source is resampled to (0 keeps each source's own rate)
+    analysis_rate: Arc<AtomicU32>,
+}
+
+impl RingProducer {
+    fn new(producer: ringbuf::HeapProd<f32>) -> Self {
+        RingProducer {
+            slot: Arc::new(Mutex::new(Some(WriterParts { ring: producer, tee: None }))),
+            overruns: Arc::new(AtomicU64::new(0)),
+            tee_overruns: Arc::new(AtomicU64::new(0)),
+            sample_rate: Arc::new(AtomicU32::new(0)),
+            analysis_rate: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Take exclusive write access to the ring buffer for a source running at `sample_rate`
+    /// Fails if another source still holds the writer
+    pub fn take_writer(&self, sample_rate: u32) -> Result<SampleWriter, String> {
+        let parts = self.lock_slot()?
+            .take()
+            .ok_or_else(|| "Ring buffer is still in use by another source".to_string())?;
+
+        let ring_rate = self.analysis_rate().unwrap_or(sample_rate);
+        let resampler = Resampler::new(sample_rate, ring_rate);
+        if resampler.is_some() {
+            info!("Resampling input from {} Hz to {} Hz", sample_rate, ring_rate);
+        }
+        self.sample_rate.store(ring_rate, Ordering::Relaxed);
+
+        Ok(SampleWriter {
+            parts: Some(parts),
+            resampler,
+            slot: self.slot.clone(),
+            overruns: self.overruns.clone(),
+            tee_overruns: self.tee_overruns.clone(),
+        })
+    }
+
+    /// Attach a second ring that receives a copy of every stereo frame
+    /// Must be called before a source takes the writer
+    pub fn attach_tee(&self, capacity_frames: usize) -> Result<RingConsumer, String> {
+        let mut slot = self.lock_slot()?;
+        let parts = slot
+            .as_mut()
+            .ok_or_else(|| "Cannot attach a tee while a source is writing".to_string())?;
+
+        let (producer, consumer) = HeapRb::<f32>::new(capacity_frames * RING_CHANNELS).split();
+        parts.tee = Some(producer);
+        Ok(consumer)
+    }
+
+    fn lock_slot(&self) -> Result<std::sync::MutexGuard<'_, Option<WriterParts>>, String> {
+        self.slot
+            .lock()
+            .map_err(|e| format!("Failed to lock ring buffer producer: {}", e))
+    }
+
+    /// Total number of samples dropped because the ring buffer was full
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Total number of samples dropped because the tee ring was full
+    pub fn tee_overruns(&self) -> u64 {
+        self.tee_overruns.load(Ordering::Relaxed)
+    }
+
+    /// Resample every source to `rate` before it reaches the ring buffer
+    /// Must be set before a source takes the writer
+    pub fn set_analysis_rate(&self, rate: Option<u32>) {
+        self.analysis_rate.store(rate.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// The fixed analysis rate, if one is configured
+    pub fn analysis_rate(&self) -> Option<u32> {
+        Some(self.analysis_rate.load(Ordering::Relaxed)).filter(|&rate| rate > 0)
+    }
+
+    /// Sample rate of the frames in the ring buffer
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Shared handle to the current sample rate, for consumers that outlive a source
+    pub fn sample_rate_handle(&self) -> Arc<AtomicU32> {
+        self.sample_rate.clone()
+    }
+}
+
+/// Exclusive, lock-free and allocation-free writer into the ring buffer
+/// Safe to use on the real-time audio thread
+pub struct SampleWriter {
+    parts: Option<WriterParts>,
+    /// Converts the source rate to the analysis rate, if they differ
+    resampler: Option<Resampler>,
+    slot: Arc<Mutex<Option<WriterParts>>>,
+    overruns: Arc<AtomicU64>,
+    tee_overruns: Arc<AtomicU64>,
+}
+
+impl SampleWriter {
+    /// Write interleaved samples to the ring buffer as stereo frames
+    /// Shared by every source so all inputs use the same channel mapping
+    ///
+    /// Mono input is duplicated to both channels, stereo passes through, and wider
+    /// layouts are downmixed (see `frame_to_stereo`)
+    pub fn write<T>(&mut self, data: &[T], channels: usize)
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let Some(parts) = self.parts.as_mut() else {
+            return;
+        };
+
+        let Some(resampler) = self.resampler.as_mut() else {
+            Self::push_frames(&mut parts.ring, data, channels, &self.overruns);
+            if let Some(tee) = parts.tee.as_mut() {
+                Self::push_frames(tee, data, channels, &self.tee_overruns);
+            }
+            return;
+        };
+
+        // Resampled output is produced frame by frame, so it is pushed the same way
+        for frame in data.chunks_exact(channels) {
+            let (left, right) = Self::frame_to_stereo(frame);
+            resampler.process(left, right, |left, right| {
+                Self::push_pair(&mut parts.ring, left, right, &self.overruns);
+                if let Some(tee) = parts.tee.as_mut() {
+                    Self::push_pair(tee, left, right, &self.tee_overruns);
+                }
+            });
+        }
+    }
+
+    /// Push a single stereo frame, counting it as dropped if the ring is full
+    fn push_pair(producer: &mut ringbuf::HeapProd<f32>, left: f32, right: f32, overruns: &AtomicU64) {
+        if producer.vacant_len() >= RING_CHANNELS {
+            producer.push_iter([left, right].into_iter());
+        } else {
+            overruns.fetch_add(RING_CHANNELS as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Push as many whole stereo frames as fit, counting the rest as dropped
+    fn push_frames<T>(producer: &mut ringbuf::HeapProd<f32>, data: &[T], channels: usize, overruns: &AtomicU64)
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        // Only whole stereo frames are pushed so channels never get out of step
+        let frames = data.chunks_exact(channels);
+        let total_frames = frames.len();
+        let fitting_frames = total_frames.min(producer.vacant_len() / RING_CHANNELS);
+
+        producer.push_iter(frames.take(fitting_frames).flat_map(|frame| {
+            let (left, right) = Self::frame_to_stereo(frame);
+            [left, right]
+        }));
+
+        if fitting_frames < total_frames {
+            // Buffer overrun - counted here and reported by the UI thread
+            let dropped = (total_frames - fitting_frames) * RING_CHANNELS;
+            overruns.fetch_add(dropped as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Map one interleaved frame of any width to a normalized (left, right) pair
+    ///
+    /// Layouts of 3 to 8 channels use the standard downmix from `downmix_gains`, with
+    /// each side divided by its total gain so full-scale input cannot clip. Wider,
+    /// unknown layouts average even-indexed channels into left and odd-indexed into right
+    pub(crate) fn frame_to_stereo<T>(frame: &[T]) -> (f32, f32)
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let sample = |s: &T| s.to_sample::<f32>();
+        match frame.len() {
+            0 => (0.0, 0.0),
+            1 => (sample(&frame[0]), sample(&frame[0])),
+            2 => (sample(&frame[0]), sample(&frame[1])),
+            channels => match downmix_gains(channels) {
+                Some(gains) => {
+                    let (mut left, mut right, mut left_total, mut right_total) = (0.0, 0.0, 0.0, 0.0);
+                    for (s, &(left_gain, right_gain)) in frame.iter().zip(gains) {
+                        let value = sample(s);
+                        left += value * left_gain;
+                        right += value * right_gain;
+                        left_total += left_gain;
+                        right_total += right_gain;
+                    }
+                    (left / left_total, right / right_total)
+                }
+                None => {
+                    let left: f32 = frame.iter().step_by(2).map(sample).sum();
+                    let right: f32 = frame.iter().skip(1).step_by(2).map(sample).sum();
+                    let left_count = frame.len().div_ceil(2) as f32;
+                    let right_count = (frame.len() / 2) as f32;
+                    (left / left_count, right / right_count)
+                }
+            },
+        }
+    }
+}
+
+impl Drop for SampleWriter {
+    /// Return the producer so another source can take it
+    fn drop(&mut self) {
+        if let Some(parts) = self.parts.take() {
+            match self.slot.lock() {
+                Ok(mut slot) => *slot = Some(parts),
+                Err(e) => error!("Failed to return ring buffer producer: {}", e),
+            }
+        }
+    }
+}
+
+/// Paces sources that are not driven by a device clock (files, pipes, generators)
+/// so samples reach the ring buffer at real-time rate
+pub(crate) struct RealtimePacer {
+    start: Instant,
+    frames_sent: u64,
+    sample_rate: u32,
+}
+
+impl RealtimePacer {
+    /// Create a pacer whose clock starts now
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        RealtimePacer {
+            start: Instant::now(),
+            frames_sent: 0,
+            sample_rate,
+        }
+    }
+
+    /// Record that `frames` were delivered and sleep until wall-clock time catches up
+    pub(crate) fn advance(&mut self, frames: usize) {
+        self.frames_sent += frames as u64;
+
+        let due = Duration::from_secs_f64(self.frames_sent as f64 / self.sample_rate as f64);
+        let elapsed = self.start.elapsed();
+        if due > elapsed {
+            std::thread::sleep(due - elapsed);
+        }
+    }
+
+    /// Restart the clock from now, e.g. after a pause or seek
+    pub(crate) fn restart(&mut self) {
+        self.start = Instant::now();
+        self.frames_sent = 0;
+    }
+
+    /// Total number of frames delivered since the clock started
+    pub(crate) fn frames_sent(&self) -> u64 {
+        self.frames_sent
+    }
+}
+
+/// Time without any audio callback before a capture stream is considered stalled
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Delay between attempts to reopen a lost capture device
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Health of an audio source as reported to the renderer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceStatus {
+    /// Samples are flowing normally
+    Active,
+    /// The input was lost and is being reopened
+    Reconnecting,
+}
+
+/// Trait for audio backends that feed samples into the ring buffer
+/// Implemented by device capture, file playback and any other input
+pub trait AudioSource {
+    /// Start delivering samples to the provided ring buffer producer
+    fn start(&mut self, producer: RingProducer) -> Result<(), String>;
+
+    /// Stop delivering samples
+    fn stop(&mut self);
+
+    /// Get the sample rate of the samples written to the ring buffer
+    fn sample_rate(&self) -> u32;
+
+    /// Get the number of channels produced by the underlying input
+    /// (samples are mapped to stereo before they reach the ring buffer)
+    fn channels(&self) -> u16;
+
+    /// Get a human-readable name for this source
+    fn name(&self) -> &str;
+
+    /// Check source health and perform any recovery work
+    /// Called regularly from the render loop; sources without recovery are always active
+    fn poll(&mut self) -> SourceStatus {
+        SourceStatus::Active
+    }
+
+    /// Transport controls for sources that can pause, seek and loop
+    fn playback(&self) -> Option<Arc<PlaybackControl>> {
+        None
+    }
+}
+
+/// Capture parameters requested on the command line (None keeps the device default)
+#[derive(Debug, Clone, Default)]
+pub struct CaptureOptions {
+    /// Host audio API name, e.g. ALSA or JACK
+    pub host: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub buffer_frames: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+impl CaptureOptions {
+    /// Whether any stream parameter was explicitly requested
+    fn is_default(&self) -> bool {
+        self.sample_rate.is_none() && self.buffer_frames.is_none() && self.channels.is_none()
+    }
+}
+
+/// Names of the host audio APIs available on this system, default first
+pub fn available_hosts() -> Vec<String> {
+    let default_id = cpal::default_host().id();
+    let mut hosts = cpal::available_hosts();
+    hosts.sort_by_key(|id| *id != default_id);
+    hosts.iter().map(|id| id.name().to_string()).collect()
+}
+
+/// Open a host audio API by name (case-insensitive), or the default host
+pub fn select_host(name: Option<&str>) -> Result<Host, String> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            format!("Audio host '{}' is not available. Available hosts: {}", name, available_hosts().join(", "))
+        })?;
+    cpal::host_from_id(id).map_err(|e| format!("Failed to open audio host '{}': {}", id.name(), e))
+}
+
+/// Common sample rates checked against each device's supported ranges
+const COMMON_SAMPLE_RATES: [u32; 11] = [
+    8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000,
+];
+
+/// Capabilities of an input device, as printed by --list-devices
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceInfo {
+    /// Position in the host's device list, starting at 1
+    pub index: usize,
+    /// Exact device name, accepted by --device
+    pub name: String,
+    /// Host audio API the device belongs to (e.g. ALSA, WASAPI, CoreAudio)
+    pub host: String,
+    /// Whether this is the host's default input device
+    pub is_default: bool,
+    /// Configuration used when no capture parameters are requested
+    pub default_config: Option<DeviceConfig>,
+    /// Common sample rates that fall inside a supported range
+    pub sample_rates: Vec<u32>,
+    /// Supported channel counts
+    pub channels: Vec<u16>,
+    /// Supported native sample formats
+    pub sample_formats: Vec<String>,
+    /// Every supported configuration range as reported by the driver
+    pub configs: Vec<DeviceConfigRange>,
+}
+
+/// A single stream configuration
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+}
+
+/// A supported range of stream configurations
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceConfigRange {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+    /// Buffer size limits in frames (None if the driver doesn't report them)
+    pub min_buffer_frames: Option<u32>,
+    pub max_buffer_frames: Option<u32>,
+}
+
+impl DeviceInfo {
+    /// Query the capabilities of an input device
+    fn query(index: usize, device: &Device, host: &Host, default_name: Option<&str>) -> Option<Self> {
+        let name = device.name().ok()?;
+
+        let default_config = device.default_input_config().ok().map(|config| DeviceConfig {
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            sample_format: config.sample_format().to_string(),
+        });
+
+        let configs: Vec<DeviceConfigRange> = device
+            .supported_input_configs()
+            .map(|ranges| ranges.map(|range| DeviceConfigRange::from_range(&range)).collect())
+            .unwrap_or_default();
+
+        Some(DeviceInfo {
+            index,
+            is_default: default_name == Some(name.as_str()),
+            name,
+            host: host.id().name().to_string(),
+            ..Self::from_configs(default_config, configs)
+        })
+    }
+
+    /// Summarize supported configuration ranges into rates, channels and formats
+    fn from_configs(default_config: Option<DeviceConfig>, configs: Vec<DeviceConfigRange>) -> Self {
+        let sample_rates = COMMON_SAMPLE_RATES
+            .iter()
+            .copied()
+            .filter(|&rate| configs.iter().any(|c| c.min_sample_rate <= rate && rate <= c.max_sample_rate))
+            .collect();
+
+        let mut channels: Vec<u16> = configs.iter().map(|c| c.channels).collect();
+        channels.sort_unstable();
+        channels.dedup();
+
+        let mut sample_formats: Vec<String> = Vec::new();
+        for config in &configs {
+            if !sample_formats.contains(&config.sample_format) {
+                sample_formats.push(config.sample_format.clone());
+            }
+        }
+
+        DeviceInfo {
+            index: 0,
+            name: String::new(),
+            host: String::new(),
+            is_default: false,
+            default_config,
+            sample_rates,
+            channels,
+            sample_formats,
+            configs,
+        }
+    }
+
+    /// Multi-line human-readable description for the device list
+    pub fn describe(&self) -> String {
+        let join = |values: Vec<String>| {
+            if values.is_empty() {
+                "unknown".to_string()
+            } else {
+                values.join(", ")
+            }
+        };
+
+        let mut lines = vec![format!(
+            "  {}. {}{}",
+            self.index,
+            self.name,
+            if self.is_default { " (default)" } else { "" }
+        )];
+        lines.push(format!("     host:         {}", self.host));
+        if let Some(config) = &self.default_config {
+            lines.push(format!(
+                "     default:      {} ch, {} Hz, {}",
+                config.channels, config.sample_rate, config.sample_format
+            ));
+        }
+        lines.push(format!("     sample rates: {}", join(self.sample_rates.iter().map(|r| r.to_string()).collect())));
+        lines.push(format!("     channels:     {}", join(self.channels.iter().map(|c| c.to_string()).collect())));
+        lines.push(format!("     formats:      {}", join(self.sample_formats.clone())));
+        lines.join("\n")
+    }
+}
+
+impl DeviceConfigRange {
+    fn from_range(range: &SupportedStreamConfigRange) -> Self {
+        let (min_buffer_frames, max_buffer_frames) = match range.buffer_size() {
+            SupportedBufferSize::Range { min, max } => (Some(*min), Some(*max)),
+            SupportedBufferSize::Unknown => (None, None),
+        };
+        DeviceConfigRange {
+            channels: range.channels(),
+            min_sample_rate: range.min_sample_rate().0,
+            max_sample_rate: range.max_sample_rate().0,
+            sample_format: range.sample_format().to_string(),
+            min_buffer_frames,
+            max_buffer_frames,
+        }
+    }
+}
+
+/// Stream health shared between the audio callback and the owning AudioProcessor
+struct StreamHealth {
+    /// Reference point for `last_data_ms`
+    epoch: Instant,
+    /// Milliseconds since `epoch` at which the last callback delivered data
+    last_data_ms: AtomicU64,
+    /// Set by the stream error callback
+    failed: AtomicBool,
+}
+
+impl StreamHealth {
+    fn new() -> Self {
+        StreamHealth {
+            epoch: Instant::now(),
+            last_data_ms: AtomicU64::new(0),
+            failed: AtomicBool::new(false),
+        }
+    }
+
+    /// Milliseconds since `epoch`
+    fn now_ms(&self) -> u64 {
+        self.epoch.elapsed().as_millis() as u64
+    }
+
+    /// Record that the stream delivered data
+    fn mark_data(&self) {
+        self.mark_data_at(self.now_ms());
+    }
+
+    /// Record that the stream delivered data `now_ms` after `epoch`
+    fn mark_data_at(&self, now_ms: u64) {
+        self.last_data_ms.store(now_ms, Ordering::Relaxed);
+    }
+
+    /// Clear error state and restart the stall timer for a fresh stream
+    fn reset(&self) {
+        self.failed.store(false, Ordering::Relaxed);
+        self.mark_data();
+    }
+
+    /// Whether the stream reported an error or stopped delivering data
+    fn is_lost(&self) -> bool {
+        self.is_lost_at(self.now_ms())
+    }
+
+    /// Whether the stream was lost as of `now_ms` after `epoch`
+    fn is_lost_at(&self, now_ms: u64) -> bool {
+        let idle_ms = now_ms.saturating_sub(self.last_data_ms.load(Ordering::Relaxed));
+        self.failed.load(Ordering::Relaxed) || idle_ms > STALL_TIMEOUT.as_millis() as u64
+    }
 }
 
 /// Audio processor that captures audio from system devices
 pub struct AudioProcessor {
+    host_id: HostId,
     device: Device,
+    device_name: String,
+    options: CaptureOptions,
     config: StreamConfig,
+    sample_format: SampleFormat,
     stream: Option<Stream>,
     sample_producer: Option<RingProducer>,
+    health: Arc<StreamHealth>,
+    /// When the next reconnect attempt is due, if the stream was lost
+    reconnect_at: Option<Instant>,
 }
 
 impl AudioProcessor {
     /// Create a new AudioProcessor with the specified device name or default device
-    pub fn new(device_name: Option<&str>) -> Result<Self, String> {
-        let host = cpal::default_host();
+    pub fn new(device_name: Option<&str>, options: &CaptureOptions) -> Result<Self, String> {
+        let host = select_host(options.host.as_deref())?;
         
         // Get the audio device
         let device = if let Some(name) = device_name {
@@ -48,120 +658,321 @@ impl AudioProcessor {
                 .ok_or_else(|| "No default input device available".to_string())?
         };
 
-        info!("Using audio device: {}", device.name().unwrap_or_else(|_| "Unknown".to_string()));
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+        info!("Using audio device: {} (host: {})", device_name, host.id().name());
+
+        let (config, sample_format) = Self::configure_device(&device, options)?;
 
+        Ok(AudioProcessor {
+            host_id: host.id(),
+            device,
+            device_name,
+            options: options.clone(),
+            config,
+            sample_format,
+            stream: None,
+            sample_producer: None,
+            health: Arc::new(StreamHealth::new()),
+            reconnect_at: None,
+        })
+    }
+
+    /// Resolve the stream config and sample format to use for a device
+    fn configure_device(
+        device: &Device,
+        options: &CaptureOptions,
+    ) -> Result<(StreamConfig, SampleFormat), String> {
         // Get the default input config
-        let supported_config = device
+        let default_config = device
             .default_input_config()
             .map_err(|e| format!("Failed to get default input config: {}", e))?;
 
+        // Check explicitly requested parameters against what the device supports
+        let supported_config = if options.is_default() {
+            default_config
+        } else {
+            Self::select_stream_config(device, &default_config, options)?
+        };
+
         debug!("Supported config: {:?}", supported_config);
 
-        // Create stream config with 44.1kHz sample rate and f32 format
-        let config = Self::create_stream_config(supported_config)?;
+        // Capture in the device's native sample format; samples are converted to f32 in the callback
+        let sample_format = supported_config.sample_format();
 
-        info!("Stream config: sample_rate={}, channels={}", 
-              config.sample_rate.0, config.channels);
+        // Create stream config with 44.1kHz sample rate unless a rate was requested
+        let config = Self::create_stream_config(supported_config, options)?;
 
-        Ok(AudioProcessor {
-            device,
-            config,
-            stream: None,
-            sample_producer: None,
-        })
+        info!("Stream config: sample_rate={}, channels={}, format={}", 
+              config.sample_rate.0, config.channels, sample_format);
+
+        Ok((config, sample_format))
+    }
+
+    /// Reopen the lost device by its exact name, falling back to the default input device
+    /// The new stream keeps the previous sample rate; without a fixed analysis rate, a device
+    /// that can't provide it is retried later
+    fn reconnect(&mut self) -> Result<(), String> {
+        let producer = self.sample_producer.clone()
+            .ok_or_else(|| "Audio capture was not started".to_string())?;
+
+        let host = cpal::host_from_id(self.host_id)
+            .map_err(|e| format!("Failed to open audio host '{}': {}", self.host_id.name(), e))?;
+        let same_device = host.input_devices().ok().and_then(|mut devices| {
+            devices.find(|d| d.name().map(|n| n == self.device_name).unwrap_or(false))
+        });
+        let device = match same_device {
+            Some(device) => device,
+            None => host.default_input_device()
+                .ok_or_else(|| "No input device available".to_string())?,
+        };
+
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+        // The FFT band layout was built for the current rate, so the new stream must match it
+        // unless a fixed analysis rate lets the resampler absorb the difference
+        let pinned = CaptureOptions {
+            sample_rate: Some(self.config.sample_rate.0),
+            ..self.options.clone()
+        };
+        let (config, sample_format) = match producer.analysis_rate() {
+            Some(_) => Self::configure_device(&device, &pinned)
+                .or_else(|_| Self::configure_device(&device, &self.options))?,
+            None => Self::configure_device(&device, &pinned)?,
+        };
+
+        self.device = device;
+        self.device_name = device_name;
+        self.config = config;
+        self.sample_format = sample_format;
+        self.start(producer)?;
+
+        info!("Reconnected to audio device: {}", self.device_name);
+        Ok(())
     }
 
     /// Find a device by name
     fn find_device_by_name(host: &Host, name: &str) -> Result<Device, String> {
-        let devices = host.input_devices()
-            .map_err(|e| format!("Failed to enumerate input devices: {}", e))?;
+        let devices: Vec<Device> = host.input_devices()
+            .map_err(|e| format!("Failed to enumerate input devices: {}", e))?
+            .collect();
+
+        // Prefer an exact name match so picking from a list never selects a similarly named device
+        let device_name = |device: &Device| device.name().unwrap_or_default();
+        if let Some(device) = devices.iter().find(|d| device_name(d) == name) {
+            return Ok(device.clone());
+        }
 
         for device in devices {
-            if let Ok(device_name) = device.name() {
-                if device_name.to_lowercase().contains(&name.to_lowercase()) {
-                    return Ok(device);
-                }
+            if device_name(&device).to_lowercase().contains(&name.to_lowercase()) {
+                return Ok(device);
             }
         }
 
         // Device not found, list available devices
-        let available = Self::list_devices_internal(host);
+        let available: Vec<String> = Self::input_device_names(host)
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("  {}. {}", i + 1, name))
+            .collect();
         Err(format!(
             "Device '{}' not found. Available devices:\n{}",
             name,
-            available.join("\n")
+            if available.is_empty() { "  No input devices found".to_string() } else { available.join("\n") }
         ))
     }
 
-    /// Create a stream config with 44.1kHz sample rate and f32 format
-    fn create_stream_config(supported: SupportedStreamConfig) -> Result<StreamConfig, String> {
+    /// Pick a supported input configuration matching the requested options
+    /// Unrequested parameters fall back to the device default, and the default
+    /// sample format is preferred when several configurations match
+    fn select_stream_config(
+        device: &Device,
+        default_config: &SupportedStreamConfig,
+        options: &CaptureOptions,
+    ) -> Result<SupportedStreamConfig, String> {
+        let ranges: Vec<SupportedStreamConfigRange> = device
+            .supported_input_configs()
+            .map_err(|e| format!("Failed to query supported input configs: {}", e))?
+            .collect();
+        Self::choose_stream_config(&ranges, default_config, options)
+    }
+
+    /// Choose among a device's supported ranges, listing them all when none match
+    fn choose_stream_config(
+        ranges: &[SupportedStreamConfigRange],
+        default_config: &SupportedStreamConfig,
+        options: &CaptureOptions,
+    ) -> Result<SupportedStreamConfig, String> {
+        let channels = options.channels.unwrap_or(default_config.channels());
+        let sample_rate = options.sample_rate.unwrap_or(default_config.sample_rate().0);
+
+        let mut candidates: Vec<&SupportedStreamConfigRange> = ranges
+            .iter()
+            .filter(|range| {
+                range.channels() == channels
+                    && range.min_sample_rate().0 <= sample_rate
+                    && sample_rate <= range.max_sample_rate().0
+                    && Self::supports_buffer_frames(range.buffer_size(), options.buffer_frames)
+            })
+            .collect();
+        candidates.sort_by_key(|range| range.sample_format() != default_config.sample_format());
+
+        match candidates.first() {
+            Some(range) => Ok(range.with_sample_rate(cpal::SampleRate(sample_rate))),
+            None => {
+                let buffer = options
+                    .buffer_frames
+                    .map(|frames| format!(", buffer {} frames", frames))
+                    .unwrap_or_default();
+                let supported: Vec<String> = ranges
+                    .iter()
+                    .map(|range| format!("  {}", Self::describe_config_range(range)))
+                    .collect();
+                Err(format!(
+                    "Device does not support {} ch at {} Hz{}. Supported input configurations:\n{}",
+                    channels,
+                    sample_rate,
+                    buffer,
+                    supported.join("\n")
+                ))
+            }
+        }
+    }
+
+    /// Check a requested buffer size against the device's supported range
+    fn supports_buffer_frames(supported: &SupportedBufferSize, requested: Option<u32>) -> bool {
+        match (supported, requested) {
+            (SupportedBufferSize::Range { min, max }, Some(frames)) => *min <= frames && frames <= *max,
+            // Devices that don't report a range get the request passed through
+            _ => true,
+        }
+    }
+
+    /// Format a supported configuration range for display
+    fn describe_config_range(range: &SupportedStreamConfigRange) -> String {
+        let buffer = match range.buffer_size() {
+            SupportedBufferSize::Range { min, max } => format!("buffer {}-{} frames", min, max),
+            SupportedBufferSize::Unknown => "buffer size unknown".to_string(),
+        };
+        format!(
+            "{} ch, {}-{} Hz, {}, {}",
+            range.channels(),
+            range.min_sample_rate().0,
+            range.max_sample_rate().0,
+            range.sample_format(),
+            buffer
+        )
+    }
+
+    /// Create a stream config with 44.1kHz sample rate, or the exact rate and
+    /// buffer size when they were requested
+    fn create_stream_config(
+        supported: SupportedStreamConfig,
+        options: &CaptureOptions,
+    ) -> Result<StreamConfig, String> {
         let desired_sample_rate = cpal::SampleRate(44100);
         let actual_sample_rate = supported.sample_rate();
         
         // Use 44.1kHz if close to the device's sample rate, otherwise use device default
-        let sample_rate = if (actual_sample_rate.0 as i32 - 44100).abs() < 1000 {
+        let sample_rate = if options.sample_rate.is_some() {
+            actual_sample_rate
+        } else if (actual_sample_rate.0 as i32 - 44100).abs() < 1000 {
             desired_sample_rate
         } else {
             warn!("Device doesn't support 44.1kHz, using default: {}", actual_sample_rate.0);
             actual_sample_rate
         };
 
+        let buffer_size = match options.buffer_frames {
+            Some(frames) => cpal::BufferSize::Fixed(frames),
+            None => cpal::BufferSize::Default,
+        };
+
         let config = StreamConfig {
             channels: supported.channels(),
             sample_rate,
-            buffer_size: cpal::BufferSize::Default,
+            buffer_size,
         };
 
         Ok(config)
     }
 
-    /// List all available audio input devices
-    pub fn list_devices() -> Vec<String> {
-        let host = cpal::default_host();
-        Self::list_devices_internal(&host)
-    }
-
-    /// Internal helper to list devices
-    fn list_devices_internal(host: &Host) -> Vec<String> {
-        let mut devices = Vec::new();
-
-        if let Ok(input_devices) = host.input_devices() {
-            for (i, device) in input_devices.enumerate() {
-                if let Ok(name) = device.name() {
-                    devices.push(format!("  {}. {}", i + 1, name));
-                }
-            }
-        }
+    /// List all input devices of a host (the default host if None) with their capabilities
+    pub fn list_devices(host: Option<&str>) -> Result<Vec<DeviceInfo>, String> {
+        let host = select_host(host)?;
+        let default_name = host.default_input_device().and_then(|d| d.name().ok());
 
-        if devices.is_empty() {
-            devices.push("  No input devices found".to_string());
-        }
+        let devices = host.input_devices()
+            .map_err(|e| format!("Failed to enumerate input devices: {}", e))?;
+        Ok(devices
+            .enumerate()
+            .filter_map(|(i, device)| DeviceInfo::query(i + 1, &device, &host, default_name.as_deref()))
+            .collect())
+    }
 
-        devices
+    /// Get the names of all input devices of a host (the default host if None)
+    pub fn device_names(host: Option<&str>) -> Vec<String> {
+        select_host(host)
+            .map(|host| Self::input_device_names(&host))
+            .unwrap_or_default()
     }
 
-    /// Start capturing audio with the provided ring buffer producer
-    pub fn start(&mut self, producer: RingProducer) -> Result<(), String> {
-        self.sample_producer = Some(producer);
+    /// Internal helper to get the input device names of a host
+    fn input_device_names(host: &Host) -> Vec<String> {
+        host.input_devices()
+            .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+            .unwrap_or_default()
+    }
 
+    /// Build an input stream for sample type `T`, converting samples to normalized f32
+    /// The writer moves into the callback, which then never locks or allocates
+    fn build_stream<T>(&self, mut writer: SampleWriter) -> Result<Stream, String>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
         let channels = self.config.channels as usize;
-        let producer_clone = self.sample_producer.as_ref().unwrap().clone();
+        let health = self.health.clone();
+        let error_health = self.health.clone();
 
-        // Create the input stream
-        let stream = self.device
+        self.device
             .build_input_stream(
                 &self.config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    Self::audio_callback(data, &producer_clone, channels);
+                move |data: &[T], _: &cpal::InputCallbackInfo| {
+                    writer.write(data, channels);
+                    health.mark_data();
                 },
-                |err| {
+                move |err| {
                     error!("Audio stream error: {}", err);
+                    error_health.failed.store(true, Ordering::Relaxed);
                 },
                 None,
             )
-            .map_err(|e| format!("Failed to build input stream: {}", e))?;
+            .map_err(|e| format!("Failed to build input stream: {}", e))
+    }
+}
+
+impl AudioSource for AudioProcessor {
+    /// Start capturing audio with the provided ring buffer producer
+    fn start(&mut self, producer: RingProducer) -> Result<(), String> {
+        // Release any writer held by a previous stream before taking it again
+        self.stream = None;
+        let writer = producer.take_writer(self.config.sample_rate.0)?;
+        self.sample_producer = Some(producer);
+
+        // Create the input stream matching the device's sample format
+        let stream = match self.sample_format {
+            SampleFormat::F32 => self.build_stream::<f32>(writer),
+            SampleFormat::F64 => self.build_stream::<f64>(writer),
+            SampleFormat::I8 => self.build_stream::<i8>(writer),
+            SampleFormat::I16 => self.build_stream::<i16>(writer),
+            SampleFormat::I32 => self.build_stream::<i32>(writer),
+            SampleFormat::U8 => self.build_stream::<u8>(writer),
+            SampleFormat::U16 => self.build_stream::<u16>(writer),
+            SampleFormat::U32 => self.build_stream::<u32>(writer),
+            other => Err(format!("Unsupported sample format: {}", other)),
+        }?;
 
         // Start the stream
+        self.health.reset();
         stream.play()
             .map_err(|e| format!("Failed to start audio stream: {}", e))?;
 
@@ -171,52 +982,150 @@ impl AudioProcessor {
         Ok(())
     }
 
-    /// Audio callback that writes samples to the ring buffer
-    fn audio_callback(data: &[f32], producer: &RingProducer, channels: usize) {
-        // Lock the producer to write samples
-        let mut producer = match producer.lock() {
-            Ok(p) => p,
-            Err(e) => {
-                error!("Failed to lock ring buffer producer: {}", e);
-                return;
-            }
-        };
-
-        // Convert multi-channel audio to mono by averaging channels
-        if channels == 1 {
-            // Mono audio - write directly
-            let written = producer.push_slice(data);
-            if written < data.len() {
-                // Buffer overrun - some samples were dropped
-                warn!("Ring buffer overrun: dropped {} samples", data.len() - written);
-            }
-        } else {
-            // Multi-channel audio - convert to mono
-            let mono_samples: Vec<f32> = data
-                .chunks_exact(channels)
-                .map(|frame| frame.iter().sum::<f32>() / channels as f32)
-                .collect();
-
-            let written = producer.push_slice(&mono_samples);
-            if written < mono_samples.len() {
-                warn!("Ring buffer overrun: dropped {} samples", mono_samples.len() - written);
-            }
-        }
-    }
-
     /// Stop capturing audio
-    pub fn stop(&mut self) {
+    fn stop(&mut self) {
         if let Some(stream) = self.stream.take() {
             drop(stream);
             info!("Audio capture stopped");
         }
         self.sample_producer = None;
+        self.reconnect_at = None;
     }
 
     /// Get the sample rate
-    pub fn sample_rate(&self) -> u32 {
+    fn sample_rate(&self) -> u32 {
         self.config.sample_rate.0
     }
+    
+    /// Get the number of channels captured from the device
+    fn channels(&self) -> u16 {
+        self.config.channels
+    }
+
+    /// Get the device name
+    fn name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Detect stream errors or stalled sample flow and periodically try to reconnect
+    fn poll(&mut self) -> SourceStatus {
+        if self.sample_producer.is_none() {
+            return SourceStatus::Active;
+        }
+
+        if self.reconnect_at.is_none() {
+            if !self.health.is_lost() {
+                return SourceStatus::Active;
+            }
+
+            warn!("Lost audio stream from '{}', reconnecting", self.device_name);
+            self.stream = None;
+            self.reconnect_at = Some(Instant::now());
+        }
+
+        if self.reconnect_at.is_some_and(|due| Instant::now() >= due) {
+            match self.reconnect() {
+                Ok(()) => {
+                    self.reconnect_at = None;
+                    return SourceStatus::Active;
+                }
+                Err(e) => {
+                    debug!("Reconnect attempt failed: {}", e);
+                    self.stream = None;
+                    self.reconnect_at = Some(Instant::now() + RECONNECT_INTERVAL);
+                }
+            }
+        }
+
+        SourceStatus::Reconnecting
+    }
+}
+
+/// Owns the active audio source and the ring buffer producer it feeds,
+/// so the input can be replaced at runtime without touching the FFT thread
+pub struct AudioInput {
+    source: Box<dyn AudioSource>,
+    producer: RingProducer,
+    options: CaptureOptions,
+}
+
+impl AudioInput {
+    /// Wrap a source together with the producer and capture options used for new devices
+    pub fn new(source: Box<dyn AudioSource>, producer: RingProducer, options: CaptureOptions) -> Self {
+        AudioInput {
+            source,
+            producer,
+            options,
+        }
+    }
+
+    /// Start the current source
+    pub fn start(&mut self) -> Result<(), String> {
+        self.source.start(self.producer.clone())
+    }
+
+    /// Sample rate of the frames reaching the analysis (the analysis rate when resampling)
+    pub fn ring_sample_rate(&self) -> u32 {
+        self.producer.sample_rate()
+    }
+
+    /// Stop the current source
+    pub fn stop(&mut self) {
+        self.source.stop();
+    }
+
+    /// Get the current source
+    pub fn source(&self) -> &dyn AudioSource {
+        self.source.as_ref()
+    }
+
+    /// Get the current source mutably
+    pub fn source_mut(&mut self) -> &mut dyn AudioSource {
+        self.source.as_mut()
+    }
+
+    /// Total number of samples dropped because the ring buffer was full
+    pub fn overruns(&self) -> u64 {
+        self.producer.overruns()
+    }
+
+    /// Names of the input devices on the configured host
+    pub fn device_names(&self) -> Vec<String> {
+        AudioProcessor::device_names(self.options.host.as_deref())
+    }
+
+    /// Replace the current source with capture from the named device
+    /// The device must run at the current sample rate so the FFT band layout stays valid,
+    /// unless a fixed analysis rate lets it fall back to the configured capture options
+    pub fn switch_to_device(&mut self, device_name: &str) -> Result<(), String> {
+        let pinned = CaptureOptions {
+            sample_rate: Some(self.source.sample_rate()),
+            ..self.options.clone()
+        };
+        let processor = match self.producer.analysis_rate() {
+            Some(_) => AudioProcessor::new(Some(device_name), &pinned)
+                .or_else(|_| AudioProcessor::new(Some(device_name), &self.options))?,
+            None => AudioProcessor::new(Some(device_name), &pinned)?,
+        };
+        self.switch_to(Box::new(processor))
+    }
+
+    /// Stop the current source and start `source` in its place
+    /// If `source` fails to start, the previous source is restarted
+    fn switch_to(&mut self, mut source: Box<dyn AudioSource>) -> Result<(), String> {
+        self.source.stop();
+
+        if let Err(e) = source.start(self.producer.clone()) {
+            if let Err(restart_error) = self.start() {
+                error!("Failed to restart previous audio source: {}", restart_error);
+            }
+            return Err(e);
+        }
+
+        info!("Switched audio input to: {}", source.name());
+        self.source = source;
+        Ok(())
+    }
 }
 
 impl Drop for AudioProcessor {
@@ -224,3 +1133,251 @@ impl Drop for AudioProcessor {
         self.stop();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_to_stereo() {
+        assert_eq!(SampleWriter::frame_to_stereo(&[0.5f32]), (0.5, 0.5));
+        assert_eq!(SampleWriter::frame_to_stereo(&[0.25f32, -0.25]), (0.25, -0.25));
+        // 5.1 (L R C LFE Ls Rs): centre and LFE at -3 dB on both sides, surrounds on their own
+        let total = 2.0 + 2.0 * SHARED_GAIN;
+        let (left, right) = SampleWriter::frame_to_stereo(&[0.0f32, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert!((left - 1.0 / total).abs() < 1e-6 && right == 0.0, "({}, {})", left, right);
+        let (left, right) = SampleWriter::frame_to_stereo(&[0.0f32, 0.0, 1.0, 1.0, 0.0, 0.0]);
+        let shared = 2.0 * SHARED_GAIN / total;
+        assert!((left - shared).abs() < 1e-6 && (right - shared).abs() < 1e-6, "({}, {})", left, right);
+        // Full scale on every channel stays within range
+        let (left, right) = SampleWriter::frame_to_stereo(&[1.0f32; 6]);
+        assert!((left - 1.0).abs() < 1e-6 && (right - 1.0).abs() < 1e-6);
+        // Unknown wide layouts: even channels feed left, odd channels feed right
+        let (left, right) = SampleWriter::frame_to_stereo(&[0.2f32, 0.6, 0.4, 0.2, 0.0, 0.4, 0.3, 0.1, 0.2]);
+        assert!((left - 0.22).abs() < 1e-6 && (right - 0.325).abs() < 1e-6, "({}, {})", left, right);
+        // Integer samples are normalized
+        assert_eq!(SampleWriter::frame_to_stereo(&[i16::MIN, 0]), (-1.0, 0.0));
+    }
+
+    #[test]
+    fn test_writer_writes_interleaved_stereo() {
+        let (producer, mut consumer) = create_ring_buffer();
+        producer.take_writer(44100).unwrap().write(&[0.1f32, 0.2], 1);
+
+        let mut samples = [0.0; 4];
+        assert_eq!(consumer.pop_slice(&mut samples), 4);
+        assert_eq!(samples, [0.1, 0.1, 0.2, 0.2]);
+    }
+
+    #[test]
+    fn test_writer_is_exclusive_and_returned_on_drop() {
+        let (producer, _consumer) = create_ring_buffer();
+
+        let writer = producer.take_writer(44100).unwrap();
+        assert!(producer.take_writer(44100).is_err());
+
+        drop(writer);
+        assert!(producer.take_writer(44100).is_ok());
+    }
+
+    #[test]
+    fn test_tee_receives_a_copy() {
+        let (producer, mut consumer) = create_ring_buffer();
+        let mut tee = producer.attach_tee(16).unwrap();
+        producer.take_writer(44100).unwrap().write(&[0.5f32, -0.5], 2);
+
+        let mut samples = [0.0; 2];
+        assert_eq!(consumer.pop_slice(&mut samples), 2);
+        assert_eq!(tee.pop_slice(&mut samples), 2);
+        assert_eq!(samples, [0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_writer_counts_overruns() {
+        let (producer, _consumer) = create_ring_buffer();
+        let mut writer = producer.take_writer(44100).unwrap();
+
+        let samples = vec![0.0f32; RING_BUFFER_CAPACITY + 10];
+        writer.write(&samples, 1);
+        assert_eq!(producer.overruns(), 10 * RING_CHANNELS as u64);
+    }
+
+    /// Source that only tracks whether it is running, optionally refusing to start
+    struct FakeSource {
+        name: &'static str,
+        running: Arc<AtomicBool>,
+        fail_start: bool,
+    }
+
+    impl FakeSource {
+        fn new(name: &'static str, fail_start: bool) -> (Box<Self>, Arc<AtomicBool>) {
+            let running = Arc::new(AtomicBool::new(false));
+            (Box::new(FakeSource { name, running: running.clone(), fail_start }), running)
+        }
+    }
+
+    impl AudioSource for FakeSource {
+        fn start(&mut self, _producer: RingProducer) -> Result<(), String> {
+            if self.fail_start {
+                return Err("device busy".to_string());
+            }
+            self.running.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn stop(&mut self) {
+            self.running.store(false, Ordering::Relaxed);
+        }
+
+        fn sample_rate(&self) -> u32 {
+            44100
+        }
+
+        fn channels(&self) -> u16 {
+            2
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+    }
+
+    #[test]
+    fn test_failed_device_switch_keeps_current_source() {
+        let (producer, _consumer) = create_ring_buffer();
+        let (source, running) = FakeSource::new("fake", false);
+        let mut input = AudioInput::new(source, producer, CaptureOptions::default());
+        input.start().unwrap();
+
+        // A device that can't be opened leaves the current source untouched
+        assert!(input.switch_to_device("no-such-device").is_err());
+        assert!(running.load(Ordering::Relaxed));
+        assert_eq!(input.source().name(), "fake");
+
+        // A source that fails to start after the current one was stopped gets it restarted
+        let (broken, broken_running) = FakeSource::new("broken", true);
+        assert_eq!(input.switch_to(broken).unwrap_err(), "device busy");
+        assert!(running.load(Ordering::Relaxed) && !broken_running.load(Ordering::Relaxed));
+        assert_eq!(input.source().name(), "fake");
+
+        let (next, next_running) = FakeSource::new("next", false);
+        input.switch_to(next).unwrap();
+        assert!(!running.load(Ordering::Relaxed) && next_running.load(Ordering::Relaxed));
+        assert_eq!(input.source().name(), "next");
+    }
+
+    #[test]
+    fn test_stream_health_detects_stalls_and_errors() {
+        let health = StreamHealth::new();
+        let stall_ms = STALL_TIMEOUT.as_millis() as u64;
+
+        health.mark_data_at(1000);
+        assert!(!health.is_lost_at(1000 + stall_ms));
+        assert!(health.is_lost_at(1001 + stall_ms));
+
+        // Fresh data restarts the stall timer
+        health.mark_data_at(1000 + stall_ms);
+        assert!(!health.is_lost_at(1001 + stall_ms));
+
+        // A stream error counts as lost until the next reset
+        health.failed.store(true, Ordering::Relaxed);
+        assert!(health.is_lost_at(1001 + stall_ms));
+        health.reset();
+        assert!(!health.is_lost());
+    }
+
+    #[test]
+    fn test_device_info_summarizes_configs_as_json() {
+        let ranges = [
+            (2, 8000, 48000, SampleFormat::I16),
+            (1, 44100, 44100, SampleFormat::F32),
+            (2, 96000, 96000, SampleFormat::I16),
+        ];
+        let configs = ranges
+            .iter()
+            .map(|&(channels, min, max, format)| {
+                DeviceConfigRange::from_range(&SupportedStreamConfigRange::new(
+                    channels,
+                    cpal::SampleRate(min),
+                    cpal::SampleRate(max),
+                    SupportedBufferSize::Unknown,
+                    format,
+                ))
+            })
+            .collect();
+        let default_config = DeviceConfig { sample_rate: 48000, channels: 2, sample_format: "i16".to_string() };
+        let info = DeviceInfo {
+            index: 1,
+            name: "Mic".to_string(),
+            host: "ALSA".to_string(),
+            is_default: true,
+            ..DeviceInfo::from_configs(Some(default_config), configs)
+        };
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["name"], "Mic");
+        assert_eq!(json["is_default"], true);
+        assert_eq!(json["default_config"]["sample_rate"], 48000);
+        assert_eq!(json["sample_rates"], serde_json::json!([8000, 11025, 16000, 22050, 32000, 44100, 48000, 96000]));
+        assert_eq!(json["channels"], serde_json::json!([1, 2]));
+        assert_eq!(json["sample_formats"], serde_json::json!(["i16", "f32"]));
+        assert_eq!(json["configs"].as_array().unwrap().len(), 3);
+        assert_eq!(json["configs"][0]["min_buffer_frames"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_choose_stream_config_lists_supported_ranges() {
+        let ranges = [
+            SupportedStreamConfigRange::new(
+                2,
+                cpal::SampleRate(8000),
+                cpal::SampleRate(48000),
+                SupportedBufferSize::Range { min: 64, max: 4096 },
+                SampleFormat::F32,
+            ),
+            SupportedStreamConfigRange::new(
+                2,
+                cpal::SampleRate(8000),
+                cpal::SampleRate(96000),
+                SupportedBufferSize::Unknown,
+                SampleFormat::I16,
+            ),
+        ];
+        let default_config = ranges[0].with_sample_rate(cpal::SampleRate(44100));
+
+        // Matching ranges prefer the default sample format
+        let options = CaptureOptions { sample_rate: Some(48000), ..Default::default() };
+        let config = AudioProcessor::choose_stream_config(&ranges, &default_config, &options).unwrap();
+        assert_eq!((config.sample_rate().0, config.sample_format()), (48000, SampleFormat::F32));
+
+        let options = CaptureOptions { sample_rate: Some(96000), ..Default::default() };
+        let config = AudioProcessor::choose_stream_config(&ranges, &default_config, &options).unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::I16);
+
+        let options = CaptureOptions { channels: Some(6), buffer_frames: Some(256), ..Default::default() };
+        let err = AudioProcessor::choose_stream_config(&ranges, &default_config, &options).unwrap_err();
+        assert_eq!(
+            err,
+            "Device does not support 6 ch at 44100 Hz, buffer 256 frames. Supported input configurations:\n  \
+             2 ch, 8000-48000 Hz, f32, buffer 64-4096 frames\n  \
+             2 ch, 8000-96000 Hz, i16, buffer size unknown"
+        );
+    }
+
+    #[test]
+    fn test_device_config_range_reports_buffer_limits() {
+        let range = SupportedStreamConfigRange::new(
+            2,
+            cpal::SampleRate(8000),
+            cpal::SampleRate(96000),
+            SupportedBufferSize::Range { min: 64, max: 4096 },
+            SampleFormat::I16,
+        );
+        let config = DeviceConfigRange::from_range(&range);
+        assert_eq!((config.min_buffer_frames, config.max_buffer_frames), (Some(64), Some(4096)));
+
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["sample_format"], "i16");
+        assert_eq!(json["max_sample_rate"], 96000);
+    }
+}
@@ -2,6 +2,20 @@
 
 use clap::Parser;
 
+use crate::fft::{BandAggregation, BinInterpolation, FftConfig, FFT_SIZE, MAX_FFT_SIZE, MAX_HOP_SIZE, MIN_FFT_SIZE};
+use crate::generator::SignalKind;
+use crate::net::Endpoint;
+use crate::pcm::{PcmFormat, STDIN_PATH};
+use crate::playback::MAX_LATENCY_OFFSET_MS;
+use crate::record::MAX_WAV_BYTES;
+use crate::render::{DEFAULT_CEILING_DB, DEFAULT_FLOOR_DB};
+use crate::scale::{FrequencyScale, DEFAULT_MAX_FREQ, DEFAULT_MIN_FREQ};
+use crate::weighting::Weighting;
+use crate::window::{WindowKind, DEFAULT_KAISER_BETA, MAX_KAISER_BETA};
+
+/// Bytes per unit of `--record-max-mb`
+pub const BYTES_PER_MB: u64 = 1024 * 1024;
+
 /// Terminal Music Visualizer - Real-time audio visualization in your terminal
 #[derive(Parser, Debug)]
 #[command(name = "termsonic")]
@@ -11,14 +25,191 @@ pub struct CliConfig {
     #[arg(short, long)]
     pub device: Option<String>,
 
+    /// Host audio API to use, e.g. ALSA or JACK (use --list-hosts to see available hosts)
+    #[arg(long, conflicts_with_all = ["pcm", "generate", "listen"])]
+    pub host: Option<String>,
+
+    /// Capture sample rate in Hz (must be supported by the device)
+    #[arg(long, conflicts_with_all = ["input", "pcm", "generate", "listen"])]
+    pub sample_rate: Option<u32>,
+
+    /// Capture buffer size in frames (must be supported by the device)
+    #[arg(long, conflicts_with_all = ["input", "pcm", "generate", "listen"])]
+    pub buffer_frames: Option<u32>,
+
+    /// Number of capture channels (must be supported by the device)
+    #[arg(long, conflicts_with_all = ["input", "pcm", "generate", "listen"])]
+    pub channels: Option<u16>,
+
+    /// Resample all input to this rate in Hz before analysis, so every device
+    /// gets the same time and frequency resolution
+    #[arg(long)]
+    pub analysis_rate: Option<u32>,
+
+    /// FFT size in samples, a power of two from 256 to 32768
+    #[arg(long, default_value_t = FFT_SIZE)]
+    pub fft_size: usize,
+
+    /// Frames between successive FFTs (defaults to half the FFT size)
+    #[arg(long)]
+    pub hop_size: Option<usize>,
+
+    /// Window function: hann, hamming, blackman, blackman-harris, flat-top, kaiser, rectangular
+    #[arg(long, default_value = "hann")]
+    pub window: String,
+
+    /// Shape parameter of the Kaiser window (higher trades resolution for lower sidelobes)
+    #[arg(long, default_value_t = DEFAULT_KAISER_BETA)]
+    pub kaiser_beta: f32,
+
+    /// Frequency scale: linear, log, mel, bark, erb, octave, third-octave (1/3), sixth-octave (1/6)
+    #[arg(long, default_value = "log")]
+    pub scale: String,
+
+    /// Lowest analyzed frequency in Hz
+    #[arg(long, default_value_t = DEFAULT_MIN_FREQ)]
+    pub min_freq: f32,
+
+    /// Highest analyzed frequency in Hz (limited to half the sample rate)
+    #[arg(long, default_value_t = DEFAULT_MAX_FREQ)]
+    pub max_freq: f32,
+
+    /// Mapping of FFT bins onto narrow bands: none, overlap, parabolic
+    #[arg(long, default_value = "overlap")]
+    pub interpolation: String,
+
+    /// How the bins inside a band are combined: rms, energy, peak, db-mean
+    #[arg(long, default_value = "energy")]
+    pub aggregation: String,
+
+    /// Frequency weighting of the spectrum: z (flat), a, c, itu-r-468
+    #[arg(long, default_value = "z")]
+    pub weighting: String,
+
+    /// Audio file to visualize instead of a capture device (WAV, FLAC, OGG, MP3)
+    #[arg(short, long, conflicts_with = "device")]
+    pub input: Option<String>,
+
+    /// Play the input file through an output device while visualizing it
+    #[arg(long, requires = "input")]
+    pub play: bool,
+
+    /// Output device for --play (defaults to the system output device)
+    #[arg(long, requires = "play")]
+    pub output_device: Option<String>,
+
+    /// Delay the visuals by this many milliseconds so they line up with --play audio
+    #[arg(long, default_value = "0", requires = "play")]
+    pub latency_offset_ms: u32,
+
+    /// Read raw interleaved PCM from a FIFO path, or "-" for stdin
+    #[arg(long, conflicts_with_all = ["device", "input"])]
+    pub pcm: Option<String>,
+
+    /// Sample format of raw PCM input: s16le or f32le
+    #[arg(long, default_value = "s16le")]
+    pub pcm_format: String,
+
+    /// Sample rate of raw PCM input in Hz
+    #[arg(long, default_value = "44100")]
+    pub pcm_rate: u32,
+
+    /// Channel count of raw PCM input
+    #[arg(long, default_value = "2")]
+    pub pcm_channels: u16,
+
+    /// Generate a test signal instead of capturing: sine, square, sawtooth, sweep, white, pink
+    #[arg(short, long, conflicts_with_all = ["device", "input", "pcm"])]
+    pub generate: Option<String>,
+
+    /// Frequency of the generated signal in Hz (start frequency for sweeps)
+    #[arg(long, default_value = "440")]
+    pub frequency: f32,
+
+    /// Peak amplitude of the generated signal (0.0 - 1.0)
+    #[arg(long, default_value = "0.5")]
+    pub amplitude: f32,
+
+    /// End frequency of a generated sweep in Hz
+    #[arg(long, default_value = "20000")]
+    pub sweep_end: f32,
+
+    /// Duration of one generated sweep in seconds
+    #[arg(long, default_value = "10")]
+    pub sweep_seconds: f32,
+
+    /// Sample rate of the generated signal in Hz
+    #[arg(long, default_value = "44100")]
+    pub generate_rate: u32,
+
+    /// Record the visualized audio to a WAV file (32-bit float stereo)
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<String>,
+
+    /// Start a new recording file after this many MiB
+    #[arg(long, requires = "record")]
+    pub record_max_mb: Option<u64>,
+
+    /// Start a new recording file after this many seconds
+    #[arg(long, requires = "record")]
+    pub record_max_seconds: Option<u64>,
+
+    /// Input gain in dB applied before analysis (--record and --send output are unaffected)
+    #[arg(long, default_value = "0", allow_hyphen_values = true)]
+    pub gain_db: f32,
+
+    /// Enable automatic gain control on the analyzed signal (--record and --send output are unaffected)
+    #[arg(long)]
+    pub agc: bool,
+
+    /// Peak level the AGC steers towards in dBFS
+    #[arg(long, default_value = "-12", allow_hyphen_values = true, requires = "agc")]
+    pub agc_target_db: f32,
+
+    /// AGC attack time in milliseconds
+    #[arg(long, default_value = "10", requires = "agc")]
+    pub agc_attack_ms: f32,
+
+    /// AGC release time in milliseconds
+    #[arg(long, default_value = "500", requires = "agc")]
+    pub agc_release_ms: f32,
+
+    /// Maximum gain the AGC may apply in dB
+    #[arg(long, default_value = "30", requires = "agc")]
+    pub agc_max_gain_db: f32,
+
+    /// Cutoff of the DC-blocking high-pass filter in Hz, applied before analysis only (0 disables it)
+    #[arg(long, default_value = "10")]
+    pub dc_cutoff_hz: f32,
+
+    /// Receive PCM streamed by --send, e.g. udp://0.0.0.0:9000 or tcp://0.0.0.0:9000
+    #[arg(long, value_name = "URL", conflicts_with_all = ["device", "input", "pcm", "generate"])]
+    pub listen: Option<String>,
+
+    /// Stream the captured audio to a --listen peer instead of visualizing it
+    #[arg(long, value_name = "URL", conflicts_with_all = ["listen", "record"])]
+    pub send: Option<String>,
+
+    /// Sample format used by --send: s16le or f32le
+    #[arg(long, default_value = "s16le")]
+    pub net_format: String,
+
     /// Visualizer mode: spectrum, waveform, or circular
     #[arg(short, long, default_value = "spectrum")]
     pub mode: String,
 
-    /// Sensitivity multiplier (0.1 - 5.0)
+    /// Sensitivity multiplier applied to signal amplitude (0.1 - 5.0)
     #[arg(short, long, default_value = "1.0")]
     pub sensitivity: f32,
 
+    /// Level in dBFS at the bottom of the display
+    #[arg(long, default_value_t = DEFAULT_FLOOR_DB, allow_hyphen_values = true)]
+    pub floor_db: f32,
+
+    /// Level in dBFS at the top of the display (a full-scale sine reads 0 dBFS)
+    #[arg(long, default_value_t = DEFAULT_CEILING_DB, allow_hyphen_values = true)]
+    pub ceiling_db: f32,
+
     /// Color scheme as comma-separated color names (e.g., red,yellow,green,cyan,blue)
     #[arg(short, long)]
     pub colors: Option<String>,
@@ -30,6 +221,14 @@ pub struct CliConfig {
     /// List available audio devices and exit
     #[arg(long)]
     pub list_devices: bool,
+
+    /// List available host audio APIs and exit
+    #[arg(long)]
+    pub list_hosts: bool,
+
+    /// Print the device list as JSON (with --list-devices)
+    #[arg(long, requires = "list_devices")]
+    pub json: bool,
 }
 
 impl CliConfig {
@@ -48,6 +247,14 @@ impl CliConfig {
             ));
         }
 
+        // Validate display range
+        if !self.floor_db.is_finite() || !self.ceiling_db.is_finite() || self.floor_db >= self.ceiling_db {
+            return Err(format!(
+                "Display floor must be below the ceiling, got: {} to {} dBFS",
+                self.floor_db, self.ceiling_db
+            ));
+        }
+
         // Validate mode
         let valid_modes = ["spectrum", "waveform", "circular"];
         if !valid_modes.contains(&self.mode.as_str()) {
@@ -58,6 +265,174 @@ impl CliConfig {
             ));
         }
 
+        // Validate capture parameters; device support is checked when the stream is opened
+        if self.sample_rate == Some(0) {
+            return Err("Sample rate must be greater than zero".to_string());
+        }
+        if self.buffer_frames == Some(0) {
+            return Err("Buffer size must be greater than zero".to_string());
+        }
+        if self.channels == Some(0) {
+            return Err("Channel count must be greater than zero".to_string());
+        }
+
+        if let Some(rate) = self.analysis_rate {
+            if !(8000..=192000).contains(&rate) {
+                return Err(format!(
+                    "Analysis rate must be between 8000 and 192000 Hz, got: {}",
+                    rate
+                ));
+            }
+        }
+
+        if !self.fft_size.is_power_of_two() || !(MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&self.fft_size) {
+            return Err(format!(
+                "FFT size must be a power of two between {} and {}, got: {}",
+                MIN_FFT_SIZE, MAX_FFT_SIZE, self.fft_size
+            ));
+        }
+
+        if let Some(hop) = self.hop_size {
+            let max_hop = self.fft_size.min(MAX_HOP_SIZE);
+            if !(1..=max_hop).contains(&hop) {
+                return Err(format!(
+                    "Hop size must be between 1 and {} for an FFT size of {}, got: {}",
+                    max_hop, self.fft_size, hop
+                ));
+            }
+        }
+
+        WindowKind::from_name(&self.window)?;
+        if !(0.0..=MAX_KAISER_BETA).contains(&self.kaiser_beta) {
+            return Err(format!(
+                "Kaiser beta must be between 0 and {}, got: {}",
+                MAX_KAISER_BETA, self.kaiser_beta
+            ));
+        }
+
+        FrequencyScale::from_name(&self.scale)?;
+        BinInterpolation::from_name(&self.interpolation)?;
+        BandAggregation::from_name(&self.aggregation)?;
+        Weighting::from_name(&self.weighting)?;
+        if self.min_freq <= 0.0 || self.max_freq <= self.min_freq {
+            return Err(format!(
+                "Frequency range must satisfy 0 < min < max, got: {} - {} Hz",
+                self.min_freq, self.max_freq
+            ));
+        }
+
+        // Validate input file if provided
+        if let Some(ref input) = self.input {
+            if !std::path::Path::new(input).is_file() {
+                return Err(format!("Input file '{}' does not exist", input));
+            }
+        }
+
+        if self.latency_offset_ms > MAX_LATENCY_OFFSET_MS {
+            return Err(format!(
+                "Latency offset must be at most {} ms, got: {}",
+                MAX_LATENCY_OFFSET_MS, self.latency_offset_ms
+            ));
+        }
+
+        // Validate raw PCM input if provided
+        if let Some(ref pcm) = self.pcm {
+            if pcm != STDIN_PATH && !std::path::Path::new(pcm).exists() {
+                return Err(format!("PCM input '{}' does not exist", pcm));
+            }
+            PcmFormat::from_name(&self.pcm_format)?;
+            if self.pcm_rate == 0 {
+                return Err("PCM sample rate must be greater than zero".to_string());
+            }
+            if self.pcm_channels == 0 {
+                return Err("PCM channel count must be greater than zero".to_string());
+            }
+        }
+
+        // Validate network endpoints if provided
+        if let Some(ref listen) = self.listen {
+            Endpoint::parse(listen)?;
+        }
+        if let Some(ref send) = self.send {
+            Endpoint::parse(send)?;
+            PcmFormat::from_name(&self.net_format)?;
+        }
+
+        // Validate signal generator if provided
+        if let Some(ref signal) = self.generate {
+            SignalKind::from_name(signal)?;
+            if self.generate_rate == 0 {
+                return Err("Generator sample rate must be greater than zero".to_string());
+            }
+            let nyquist = self.generate_rate as f32 / 2.0;
+            for (name, value) in [("Frequency", self.frequency), ("Sweep end", self.sweep_end)] {
+                if value <= 0.0 || value >= nyquist {
+                    return Err(format!(
+                        "{} must be between 0 and {} Hz, got: {}",
+                        name, nyquist, value
+                    ));
+                }
+            }
+            if !(0.0..=1.0).contains(&self.amplitude) {
+                return Err(format!(
+                    "Amplitude must be between 0.0 and 1.0, got: {}",
+                    self.amplitude
+                ));
+            }
+            if self.sweep_seconds <= 0.0 {
+                return Err(format!(
+                    "Sweep duration must be positive, got: {}",
+                    self.sweep_seconds
+                ));
+            }
+        }
+
+        // Validate recording options if provided
+        if let Some(ref record) = self.record {
+            let path = std::path::Path::new(record);
+            let parent = path.parent().filter(|p| !p.as_os_str().is_empty());
+            if parent.is_some_and(|p| !p.is_dir()) {
+                return Err(format!("Recording directory for '{}' does not exist", record));
+            }
+            if let Some(max_mb) = self.record_max_mb {
+                if max_mb == 0 || max_mb * BYTES_PER_MB > MAX_WAV_BYTES {
+                    return Err(format!(
+                        "Recording size limit must be between 1 and {} MiB, got: {}",
+                        MAX_WAV_BYTES / BYTES_PER_MB,
+                        max_mb
+                    ));
+                }
+            }
+            if self.record_max_seconds == Some(0) {
+                return Err("Recording duration limit must be greater than zero".to_string());
+            }
+        }
+
+        // Validate preprocessing
+        if !(-60.0..=60.0).contains(&self.gain_db) {
+            return Err(format!("Gain must be between -60 and 60 dB, got: {}", self.gain_db));
+        }
+        if self.agc {
+            if self.agc_target_db > 0.0 {
+                return Err(format!("AGC target must be at most 0 dBFS, got: {}", self.agc_target_db));
+            }
+            if self.agc_attack_ms <= 0.0 || self.agc_release_ms <= 0.0 {
+                return Err("AGC attack and release times must be positive".to_string());
+            }
+            if !(0.0..=60.0).contains(&self.agc_max_gain_db) {
+                return Err(format!(
+                    "AGC maximum gain must be between 0 and 60 dB, got: {}",
+                    self.agc_max_gain_db
+                ));
+            }
+        }
+        if self.dc_cutoff_hz < 0.0 || self.dc_cutoff_hz > 200.0 {
+            return Err(format!(
+                "DC filter cutoff must be between 0 and 200 Hz, got: {}",
+                self.dc_cutoff_hz
+            ));
+        }
+
         // Validate colors if provided
         if let Some(ref colors) = self.colors {
             self.validate_colors(colors)?;
@@ -88,6 +463,14 @@ impl CliConfig {
         Ok(())
     }
 
+    /// FFT size and hop, with the hop defaulting to 50% overlap
+    pub fn fft_config(&self) -> FftConfig {
+        FftConfig {
+            size: self.fft_size,
+            hop: self.hop_size.unwrap_or_else(|| FftConfig::default_hop(self.fft_size)),
+        }
+    }
+
     /// Parse colors from the color string
     pub fn parse_colors(&self) -> Vec<String> {
         if let Some(ref colors) = self.colors {
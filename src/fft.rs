@@ -1,234 +1,533 @@
 // FFT processing module
 
 use log::{debug, warn};
-use ringbuf::traits::Consumer;
-use rustfft::{num_complex::Complex, FftPlanner};
-use std::f32::consts::PI;
+use ringbuf::traits::{Consumer, Observer};
+use realfft::{num_complex::Complex, RealFftPlanner, RealToComplex};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use crate::audio::RingConsumer;
+use crate::audio::{RingConsumer, RING_BUFFER_CAPACITY, RING_CHANNELS};
+use crate::preprocess::Preprocessor;
+use crate::scale::{BandLimits, FrequencyScale, DEFAULT_MAX_FREQ, DEFAULT_MIN_FREQ};
+use crate::weighting::Weighting;
+use crate::window::{coherent_gain, equivalent_noise_bandwidth, WindowControl, WindowKind, DEFAULT_KAISER_BETA};
 
-/// FFT size for processing (2048 samples provides good frequency resolution)
+/// Default FFT size (2048 samples provides good frequency resolution)
 pub const FFT_SIZE: usize = 2048;
 
+/// Smallest supported FFT size
+pub const MIN_FFT_SIZE: usize = 256;
+
+/// Largest supported FFT size
+pub const MAX_FFT_SIZE: usize = 32768;
+
+/// Largest hop size; a hop must fit in the ring buffer with room to spare
+pub const MAX_HOP_SIZE: usize = RING_BUFFER_CAPACITY / 2;
+
+/// Most hops processed before a spectrum is published, so a backlog of small hops
+/// can't keep the display from updating
+const MAX_HOPS_PER_UPDATE: usize = 64;
+
+/// FFT size and hop between successive transforms, in frames
+/// The hop must not exceed the FFT size
+#[derive(Debug, Clone, Copy)]
+pub struct FftConfig {
+    pub size: usize,
+    pub hop: usize,
+}
+
+impl FftConfig {
+    /// 50% overlap for the given size, limited to the largest hop the ring buffer allows
+    pub fn default_hop(size: usize) -> usize {
+        (size / 2).min(MAX_HOP_SIZE)
+    }
+}
+
+/// Linear power per FFT bin for the mono downmix and each stereo channel, scaled so a
+/// full-scale sine centered on a bin reads 1.0 (0 dBFS)
+/// Levels are converted to decibels only after bins are combined into bands
+#[derive(Debug, Clone)]
+pub struct ChannelSpectra {
+    pub mono: Vec<f32>,
+    pub left: Vec<f32>,
+    pub right: Vec<f32>,
+    /// Equivalent noise bandwidth of the window in bins, used to calibrate summed power
+    pub noise_bandwidth: f32,
+}
+
 /// FFT Engine that transforms time-domain audio samples into frequency-domain spectrum
+/// Reads interleaved stereo frames and transforms each channel separately
+///
+/// The real-to-complex plan and every buffer are created up front, so processing
+/// a block neither plans nor allocates.
 pub struct FftEngine {
     fft_size: usize,
-    planner: FftPlanner<f32>,
+    hop_size: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
     window: Vec<f32>,
-    input_buffer: Vec<Complex<f32>>,
-    output_buffer: Vec<Complex<f32>>,
+    window_kind: WindowKind,
+    window_control: Arc<WindowControl>,
+    /// Amplitude scale from |X| to full scale: 2 / (N * coherent gain)
+    amplitude_scale: f32,
+    /// Power gain of the frequency weighting for each bin
+    weighting_gains: Vec<f32>,
     sample_source: RingConsumer,
-    overlap_buffer: Vec<f32>,
+    /// Interleaved frames read for the current hop
+    samples: Vec<f32>,
+    /// The most recent `fft_size` samples of each channel, oldest first
+    history: [Vec<f32>; RING_CHANNELS],
+    /// Windowed input for the transform (overwritten by it)
+    input: Vec<f32>,
+    /// Positive-frequency spectrum of each channel (bins 0 to N/2)
+    spectra: [Vec<Complex<f32>>; RING_CHANNELS],
+    mono_spectrum: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    power: ChannelSpectra,
+    preprocessor: Option<Preprocessor>,
 }
 
 impl FftEngine {
-    /// Create a new FFT engine with the specified FFT size and sample source
-    pub fn new(fft_size: usize, sample_source: RingConsumer) -> Self {
-        let planner = FftPlanner::new();
-        let window = Self::generate_hann_window(fft_size);
+    /// Create a new FFT engine with the specified FFT size, hop size and sample source
+    /// The hop size must be between 1 and the FFT size
+    pub fn new(fft_size: usize, hop_size: usize, sample_source: RingConsumer) -> Self {
+        let fft = RealFftPlanner::<f32>::new().plan_fft_forward(fft_size);
+        let window_control = Arc::new(WindowControl::new(WindowKind::Hann, DEFAULT_KAISER_BETA));
+        let num_bins = fft_size / 2 + 1;
         
-        debug!("Initialized FFT engine with size: {}", fft_size);
+        debug!("Initialized FFT engine with size: {}, hop: {}", fft_size, hop_size);
         
-        FftEngine {
+        let mut engine = FftEngine {
             fft_size,
-            planner,
-            window,
-            input_buffer: vec![Complex::new(0.0, 0.0); fft_size],
-            output_buffer: vec![Complex::new(0.0, 0.0); fft_size],
+            hop_size,
+            window: Vec::new(),
+            window_kind: WindowKind::Hann,
+            window_control,
+            amplitude_scale: 1.0,
+            weighting_gains: vec![1.0; num_bins],
             sample_source,
-            overlap_buffer: Vec::new(),
-        }
+            samples: vec![0.0; hop_size * RING_CHANNELS],
+            history: std::array::from_fn(|_| vec![0.0; fft_size]),
+            input: fft.make_input_vec(),
+            spectra: std::array::from_fn(|_| fft.make_output_vec()),
+            mono_spectrum: fft.make_output_vec(),
+            scratch: fft.make_scratch_vec(),
+            power: ChannelSpectra {
+                mono: vec![0.0; num_bins],
+                left: vec![0.0; num_bins],
+                right: vec![0.0; num_bins],
+                noise_bandwidth: 1.0,
+            },
+            fft,
+            preprocessor: None,
+        };
+        engine.update_window();
+        engine
     }
     
-    /// Generate a Hann window function to reduce spectral leakage
-    /// Formula: w(n) = 0.5 * (1 - cos(2πn/N))
-    fn generate_hann_window(size: usize) -> Vec<f32> {
-        (0..size)
-            .map(|n| 0.5 * (1.0 - ((2.0 * PI * n as f32) / (size as f32 - 1.0)).cos()))
-            .collect()
+    /// Run gain, AGC and DC blocking on samples before they are transformed
+    pub fn set_preprocessor(&mut self, preprocessor: Preprocessor) {
+        self.preprocessor = Some(preprocessor);
     }
     
-    /// Process a block of audio samples and return frequency magnitudes in decibels
-    /// Returns None if not enough samples are available
-    pub fn process_block(&mut self) -> Option<Vec<f32>> {
-        // Calculate how many samples we need (50% overlap means we need half FFT size new samples)
-        let hop_size = self.fft_size / 2;
-        
-        // Read samples from ring buffer
-        let mut samples = vec![0.0f32; hop_size];
-        let read_count = self.sample_source.pop_slice(&mut samples);
+    /// Follow a window selection that can change while running
+    pub fn set_window_control(&mut self, window_control: Arc<WindowControl>) {
+        self.window_control = window_control;
+        self.update_window();
+    }
+    
+    /// Apply a frequency weighting curve to the power spectrum
+    pub fn set_weighting(&mut self, weighting: Weighting, sample_rate: f32) {
+        self.weighting_gains = weighting.power_gains(self.fft_size, sample_rate);
+        debug!("Using {}-weighting", weighting.name());
+    }
+    
+    /// Regenerate the window and the dBFS normalization for the current selection
+    /// A sine of amplitude A peaks at |X| = A * N * coherent gain / 2, so the scale
+    /// removes both the FFT size and the window gain
+    fn update_window(&mut self) {
+        self.window_kind = self.window_control.current();
+        self.window = self.window_kind.generate(self.fft_size, self.window_control.kaiser_beta());
+        let gain = coherent_gain(&self.window).max(1e-6);
+        self.amplitude_scale = 2.0 / (self.fft_size as f32 * gain);
+        self.power.noise_bandwidth = equivalent_noise_bandwidth(&self.window);
+        debug!("Using {} window (coherent gain {:.3})", self.window_kind.name(), gain);
+    }
+    
+    /// Process a block of audio samples into the power spectrum of each channel, read with `power`
+    /// Each block consumes one hop of new frames; the rest of the window is history
+    /// Returns false if not enough samples are available
+    pub fn process_block(&mut self) -> bool {
+        let hop_size = self.hop_size;
         
-        if read_count < hop_size {
-            // Not enough samples available
-            return None;
+        // Only consume once a full hop of stereo frames is buffered
+        if self.sample_source.occupied_len() < hop_size * RING_CHANNELS {
+            return false;
         }
         
-        // Build the full FFT input buffer with 50% overlap
-        let mut full_samples = Vec::with_capacity(self.fft_size);
+        // Read interleaved frames from ring buffer
+        self.sample_source.pop_slice(&mut self.samples);
         
-        // Add overlap from previous block (second half of previous block)
-        if self.overlap_buffer.len() == hop_size {
-            full_samples.extend_from_slice(&self.overlap_buffer);
-        } else {
-            // First block - pad with zeros
-            full_samples.resize(hop_size, 0.0);
+        if let Some(preprocessor) = self.preprocessor.as_mut() {
+            preprocessor.process(&mut self.samples);
         }
         
-        // Add new samples
-        full_samples.extend_from_slice(&samples[..hop_size]);
-        
-        // Store second half for next overlap
-        self.overlap_buffer.clear();
-        self.overlap_buffer.extend_from_slice(&full_samples[hop_size..]);
+        if self.window_control.current() != self.window_kind {
+            self.update_window();
+        }
         
-        // Apply Hann window to reduce spectral leakage
-        self.apply_window(&full_samples);
+        for channel in 0..RING_CHANNELS {
+            // Slide the window forward by one hop and append the new samples
+            // (the history starts out as silence)
+            let history = &mut self.history[channel];
+            history.copy_within(hop_size.., 0);
+            let new_samples = self.samples.iter().skip(channel).step_by(RING_CHANNELS);
+            for (slot, &sample) in history[self.fft_size - hop_size..].iter_mut().zip(new_samples) {
+                *slot = sample;
+            }
+            
+            // Apply the window to reduce spectral leakage
+            for (input, (&sample, &weight)) in self.input.iter_mut().zip(history.iter().zip(&self.window)) {
+                *input = sample * weight;
+            }
+            
+            // Compute the real-to-complex FFT (positive frequencies only)
+            if let Err(e) = self.fft.process_with_scratch(&mut self.input, &mut self.spectra[channel], &mut self.scratch) {
+                warn!("FFT failed: {}", e);
+                return false;
+            }
+        }
         
-        // Compute FFT
-        let fft = self.planner.plan_fft_forward(self.fft_size);
-        fft.process(&mut self.input_buffer);
+        // The FFT is linear, so the mono spectrum is the average of the channel spectra
+        let [left, right] = &self.spectra;
+        for (mono, (l, r)) in self.mono_spectrum.iter_mut().zip(left.iter().zip(right)) {
+            *mono = (l + r) * 0.5;
+        }
         
-        // Convert complex output to magnitude values in decibels
-        let magnitudes = self.compute_magnitudes();
+        // Convert complex output to weighted power relative to full scale
+        let (scale, weights) = (self.amplitude_scale, &self.weighting_gains);
+        Self::compute_power(&self.mono_spectrum, scale, weights, &mut self.power.mono);
+        Self::compute_power(left, scale, weights, &mut self.power.left);
+        Self::compute_power(right, scale, weights, &mut self.power.right);
         
-        Some(magnitudes)
+        true
     }
     
-    /// Apply Hann window to samples and store in input buffer
-    fn apply_window(&mut self, samples: &[f32]) {
-        for (i, &sample) in samples.iter().enumerate() {
-            self.input_buffer[i] = Complex::new(sample * self.window[i], 0.0);
-        }
+    /// Power spectra from the most recent block
+    pub fn power(&self) -> &ChannelSpectra {
+        &self.power
     }
     
-    /// Convert complex FFT output to magnitude values in decibels
-    /// Only processes positive frequencies (bins 0 to N/2) since input is real
-    fn compute_magnitudes(&self) -> Vec<f32> {
-        let num_bins = self.fft_size / 2 + 1;
-        let mut magnitudes = Vec::with_capacity(num_bins);
-        
-        for i in 0..num_bins {
-            let complex = self.input_buffer[i];
-            let magnitude = (complex.re * complex.re + complex.im * complex.im).sqrt();
-            
-            // Convert to decibels: 20 * log10(magnitude)
-            // Add small epsilon to avoid log(0)
-            let db = 20.0 * (magnitude + 1e-10).log10();
-            magnitudes.push(db);
+    /// Convert complex FFT output to power relative to full scale, (|X| * scale)²,
+    /// with the per-bin weighting gains
+    fn compute_power(spectrum: &[Complex<f32>], amplitude_scale: f32, weights: &[f32], power: &mut [f32]) {
+        let gain = amplitude_scale * amplitude_scale;
+        for ((bin, complex), &weight) in power.iter_mut().zip(spectrum).zip(weights) {
+            *bin = complex.norm_sqr() * gain * weight;
         }
-        
-        magnitudes
     }
 }
 
-/// Frequency band for logarithmic binning
+/// How FFT bins are mapped onto bands that are narrower than, or close to, one bin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinInterpolation {
+    /// Every bin touched by the band counts fully; narrow bands repeat the same bin
+    None,
+    /// Bins are weighted by how much of them overlaps the band; bands narrower than
+    /// a bin interpolate power linearly between the two nearest bins
+    Overlap,
+    /// Like `Overlap`, but narrow bands fit a parabola through the three nearest bins in dB
+    /// and skip the band aggregation
+    Parabolic,
+}
+
+impl BinInterpolation {
+    /// Parse an interpolation name as given on the command line
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "none" => Ok(BinInterpolation::None),
+            "overlap" => Ok(BinInterpolation::Overlap),
+            "parabolic" => Ok(BinInterpolation::Parabolic),
+            _ => Err(format!(
+                "Invalid interpolation '{}'. Valid options are: none, overlap, parabolic",
+                name
+            )),
+        }
+    }
+}
+
+/// How the bins inside a band are combined into one level
+/// All modes except `DbMean` work on linear power and convert to dB afterwards
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandAggregation {
+    /// Root mean square: mean power of the bins in the band
+    Rms,
+    /// Total energy of the bins in the band, as a 1/3-octave analyzer reports it;
+    /// a sinusoid inside the band reads its own level in dBFS
+    Energy,
+    /// Loudest bin in the band
+    Peak,
+    /// Mean of the bin levels in dB (understates narrow peaks)
+    DbMean,
+}
+
+impl BandAggregation {
+    /// Parse an aggregation name as given on the command line
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "rms" => Ok(BandAggregation::Rms),
+            "energy" | "sum" => Ok(BandAggregation::Energy),
+            "peak" | "max" => Ok(BandAggregation::Peak),
+            "db-mean" => Ok(BandAggregation::DbMean),
+            _ => Err(format!(
+                "Invalid aggregation '{}'. Valid options are: rms, energy, peak, db-mean",
+                name
+            )),
+        }
+    }
+}
+
+/// Level reported for silence and for bands without any bins, in dBFS
+pub const SILENCE_DB: f32 = -200.0;
+
+/// Convert linear power to decibels: 10 * log10(power)
+/// A small epsilon keeps silence finite (`SILENCE_DB`)
+fn power_to_db(power: f32) -> f32 {
+    10.0 * (power + 1e-20).log10()
+}
+
+/// Layout of the analyzer bands
+#[derive(Debug, Clone, Copy)]
+pub struct BandConfig {
+    /// Number of bands (ignored by fractional-octave scales, which use the standard bands)
+    pub num_bands: usize,
+    pub scale: FrequencyScale,
+    /// Lowest analyzed frequency in Hz
+    pub min_freq: f32,
+    /// Highest analyzed frequency in Hz (limited to the Nyquist frequency)
+    pub max_freq: f32,
+    pub interpolation: BinInterpolation,
+    pub aggregation: BandAggregation,
+}
+
+impl BandConfig {
+    /// Logarithmic bands from 20 Hz to 20 kHz
+    pub fn log(num_bands: usize) -> Self {
+        BandConfig {
+            num_bands,
+            scale: FrequencyScale::Log,
+            min_freq: DEFAULT_MIN_FREQ,
+            max_freq: DEFAULT_MAX_FREQ,
+            interpolation: BinInterpolation::Overlap,
+            aggregation: BandAggregation::Energy,
+        }
+    }
+}
+
+/// Frequency band mapped onto FFT bins
 #[derive(Debug, Clone)]
 struct FrequencyBand {
     start_bin: usize,
     end_bin: usize,
+    /// Weight of each bin from `start_bin` to `end_bin`
+    weights: Vec<f32>,
+    /// Fractional bin at the band center, set when the band is read by interpolation
+    interpolate_at: Option<f32>,
+    #[allow(dead_code)]
     center_freq: f32,
 }
 
-/// Frequency binner that maps FFT bins to logarithmic frequency bands
+/// Frequency binner that maps FFT bins to frequency bands on a chosen scale
 pub struct FrequencyBinner {
     bands: Vec<FrequencyBand>,
+    interpolation: BinInterpolation,
+    aggregation: BandAggregation,
+    #[allow(dead_code)]
     fft_size: usize,
+    #[allow(dead_code)]
     sample_rate: f32,
 }
 
 impl FrequencyBinner {
-    /// Create a new frequency binner with the specified number of bands
+    /// Create a new frequency binner with the specified number of logarithmic bands
     /// Frequency range: 20 Hz to 20 kHz (human hearing range)
     pub fn new(num_bands: usize, fft_size: usize, sample_rate: f32) -> Self {
-        let f_min = 20.0; // Minimum frequency (Hz)
-        let f_max = 20000.0; // Maximum frequency (Hz)
+        Self::with_config(&BandConfig::log(num_bands), fft_size, sample_rate)
+            .expect("default bands start below the Nyquist frequency")
+    }
+    
+    /// Create a frequency binner for the given band layout
+    /// Fails if the bands would start at or above the Nyquist frequency
+    pub fn with_config(config: &BandConfig, fft_size: usize, sample_rate: f32) -> Result<Self, String> {
+        let nyquist = sample_rate / 2.0;
+        if config.min_freq >= nyquist {
+            return Err(format!(
+                "Minimum frequency {} Hz must be below the Nyquist frequency ({} Hz at {} Hz sample rate)",
+                config.min_freq, nyquist, sample_rate
+            ));
+        }
+        let max_freq = config.max_freq.min(nyquist);
+        if max_freq < config.max_freq {
+            debug!("Limiting maximum frequency to the Nyquist frequency ({} Hz)", nyquist);
+        }
         
-        let bands = Self::calculate_logarithmic_bands(num_bands, f_min, f_max, fft_size, sample_rate);
+        let limits = config.scale.bands(config.num_bands, config.min_freq, max_freq);
+        let bands: Vec<FrequencyBand> = limits
+            .iter()
+            .map(|band| Self::map_to_bins(band, config.interpolation, fft_size, sample_rate))
+            .collect();
         
-        debug!("Created {} logarithmic frequency bands from {} Hz to {} Hz", 
-               num_bands, f_min, f_max);
+        debug!("Created {} {} frequency bands from {} Hz to {} Hz", 
+               bands.len(), config.scale.name(), config.min_freq, max_freq);
         
-        FrequencyBinner {
+        Ok(FrequencyBinner {
             bands,
+            interpolation: config.interpolation,
+            aggregation: config.aggregation,
             fft_size,
             sample_rate,
-        }
+        })
     }
     
-    /// Calculate logarithmic frequency bands
-    /// Formula: f(i) = f_min * (f_max/f_min)^(i/N)
-    fn calculate_logarithmic_bands(
-        num_bands: usize,
-        f_min: f32,
-        f_max: f32,
+    /// Map band edges in Hz to weighted FFT bins
+    /// Bin frequency = (bin_index * sample_rate) / fft_size
+    fn map_to_bins(
+        band: &BandLimits,
+        interpolation: BinInterpolation,
         fft_size: usize,
         sample_rate: f32,
-    ) -> Vec<FrequencyBand> {
-        let mut bands = Vec::with_capacity(num_bands);
-        let ratio = (f_max / f_min).powf(1.0 / num_bands as f32);
+    ) -> FrequencyBand {
+        let bin_width = sample_rate / fft_size as f32;
+        let last_bin = fft_size / 2;
+        let low = band.low / bin_width;
+        let high = band.high / bin_width;
         
-        for i in 0..num_bands {
-            // Calculate frequency range for this band
-            let freq_start = f_min * ratio.powf(i as f32);
-            let freq_end = f_min * ratio.powf((i + 1) as f32);
-            let center_freq = (freq_start + freq_end) / 2.0;
-            
-            // Convert frequencies to FFT bin indices
-            // Bin frequency = (bin_index * sample_rate) / fft_size
-            let start_bin = ((freq_start * fft_size as f32) / sample_rate).floor() as usize;
-            let end_bin = ((freq_end * fft_size as f32) / sample_rate).ceil() as usize;
-            
-            // Clamp to valid range
-            let start_bin = start_bin.min(fft_size / 2);
-            let end_bin = end_bin.min(fft_size / 2 + 1).max(start_bin + 1);
-            
-            bands.push(FrequencyBand {
+        if interpolation == BinInterpolation::None {
+            let start_bin = (low.floor() as usize).min(last_bin);
+            let end_bin = (high.ceil() as usize).min(last_bin + 1).max(start_bin + 1);
+            return FrequencyBand {
                 start_bin,
                 end_bin,
-                center_freq,
-            });
+                weights: vec![1.0; end_bin - start_bin],
+                interpolate_at: None,
+                center_freq: band.center,
+            };
         }
         
-        bands
+        // Bin k covers k - 0.5 to k + 0.5 bins; weight it by the part inside the band
+        let start_bin = ((low + 0.5).floor() as usize).min(last_bin);
+        let end_bin = ((high + 0.5).floor() as usize + 1).min(last_bin + 1).max(start_bin + 1);
+        let weights = (start_bin..end_bin)
+            .map(|k| {
+                let overlap = (high.min(k as f32 + 0.5) - low.max(k as f32 - 0.5)).max(0.0);
+                overlap.min(1.0)
+            })
+            .collect();
+        
+        // Bands narrower than a bin read the spectrum at their center instead
+        let interpolate_at = (high - low < 1.0).then_some((band.center / bin_width).min(last_bin as f32));
+        
+        FrequencyBand {
+            start_bin,
+            end_bin,
+            weights,
+            interpolate_at,
+            center_freq: band.center,
+        }
     }
     
-    /// Bin the FFT spectrum into logarithmic frequency bands
-    /// Averages multiple FFT bins for each frequency band
-    pub fn bin_spectrum(&self, fft_magnitudes: &[f32]) -> Vec<f32> {
-        let mut binned = Vec::with_capacity(self.bands.len());
-        
-        for band in &self.bands {
-            // Average all FFT bins in this frequency band
-            let mut sum = 0.0;
-            let mut count = 0;
-            
-            for bin_idx in band.start_bin..band.end_bin {
-                if bin_idx < fft_magnitudes.len() {
-                    sum += fft_magnitudes[bin_idx];
-                    count += 1;
+    /// Bin a power spectrum into frequency bands, returning band levels in dB
+    /// `noise_bandwidth` is the window's equivalent noise bandwidth in bins
+    pub fn bin_spectrum(&self, power: &[f32], noise_bandwidth: f32) -> Vec<f32> {
+        self.bands
+            .iter()
+            .map(|band| match band.interpolate_at {
+                Some(position) if self.interpolation == BinInterpolation::Parabolic => {
+                    Self::interpolate_parabolic(power, position)
+                }
+                Some(position) => {
+                    // The interpolated power stands in for one bin as wide as the band
+                    let width = band.weights.iter().sum();
+                    let bins = std::iter::once((Self::interpolate_linear(power, position), width));
+                    self.combine(bins, noise_bandwidth)
                 }
+                None => self.aggregate(power, band, noise_bandwidth),
+            })
+            .collect()
+    }
+    
+    /// Combine the weighted bins of a band into one level in dB
+    fn aggregate(&self, power: &[f32], band: &FrequencyBand, noise_bandwidth: f32) -> f32 {
+        let end_bin = band.end_bin.min(power.len());
+        let bins = power
+            .get(band.start_bin..end_bin)
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .zip(band.weights.iter().copied());
+        self.combine(bins, noise_bandwidth)
+    }
+    
+    /// Combine (power, weight) pairs into one level in dB with the selected aggregation
+    /// Bands without any weighted bins read as silence
+    fn combine(&self, bins: impl Iterator<Item = (f32, f32)>, noise_bandwidth: f32) -> f32 {
+        let mut total_weight = 0.0;
+        let mut accumulated = 0.0;
+        for (bin_power, weight) in bins.filter(|&(_, weight)| weight > 0.0) {
+            total_weight += weight;
+            match self.aggregation {
+                BandAggregation::Rms | BandAggregation::Energy => accumulated += bin_power * weight,
+                BandAggregation::Peak => accumulated = f32::max(accumulated, bin_power),
+                BandAggregation::DbMean => accumulated += power_to_db(bin_power) * weight,
             }
-            
-            let average = if count > 0 {
-                sum / count as f32
-            } else {
-                0.0
-            };
-            
-            binned.push(average);
         }
         
-        binned
+        if total_weight == 0.0 {
+            return SILENCE_DB;
+        }
+        match self.aggregation {
+            BandAggregation::Rms => power_to_db(accumulated / total_weight),
+            // Each bin also collects power leaking from its neighbours through the window
+            BandAggregation::Energy => power_to_db(accumulated / noise_bandwidth),
+            BandAggregation::Peak => power_to_db(accumulated),
+            BandAggregation::DbMean => accumulated / total_weight,
+        }
+    }
+    
+    /// Read the power spectrum at a fractional bin position by linear interpolation
+    fn interpolate_linear(power: &[f32], position: f32) -> f32 {
+        if power.len() < 2 {
+            return power.first().copied().unwrap_or(0.0);
+        }
+        
+        let k = (position.floor() as usize).min(power.len() - 2);
+        let fraction = position - k as f32;
+        power[k] + (power[k + 1] - power[k]) * fraction
+    }
+    
+    /// Read the spectrum in dB at a fractional bin position with a parabola through
+    /// the nearest bin and its neighbours
+    /// Unlike the other modes this runs on dB values, where spectral peaks are close to
+    /// parabolic, and returns the level directly without aggregation
+    fn interpolate_parabolic(power: &[f32], position: f32) -> f32 {
+        let len = power.len();
+        if len < 3 {
+            return power.first().map(|&p| power_to_db(p)).unwrap_or(SILENCE_DB);
+        }
+        
+        let k = (position.round() as usize).clamp(1, len - 2);
+        let d = position - k as f32;
+        let [a, b, c] = [power[k - 1], power[k], power[k + 1]].map(power_to_db);
+        b + d * (c - a) / 2.0 + d * d * (c - 2.0 * b + a) / 2.0
     }
     
     /// Get the number of bands
+    #[allow(dead_code)]
     pub fn num_bands(&self) -> usize {
         self.bands.len()
     }
     
     /// Adapt number of bands based on terminal width (32-64 bands)
+    #[allow(dead_code)]
     pub fn adapt_to_width(terminal_width: usize, fft_size: usize, sample_rate: f32) -> Self {
         // Use terminal width as guide, clamped to reasonable range
         let num_bands = terminal_width.clamp(32, 64);
@@ -237,13 +536,16 @@ impl FrequencyBinner {
 }
 
 /// Spectrum smoother that applies temporal smoothing to reduce visual jitter
+#[allow(dead_code)]
 pub struct SpectrumSmoother {
     smoothed_values: Vec<f32>,
     peak_values: Vec<f32>,
-    peak_decay_rate: f32,
+    /// Peak fall per frame in dB
+    peak_decay_db: f32,
     smoothing_factor: f32,
 }
 
+#[allow(dead_code, clippy::needless_range_loop)]
 impl SpectrumSmoother {
     /// Create a new spectrum smoother with the specified number of bands
     /// 
@@ -256,9 +558,9 @@ impl SpectrumSmoother {
                num_bands, smoothing_factor);
         
         SpectrumSmoother {
-            smoothed_values: vec![0.0; num_bands],
-            peak_values: vec![0.0; num_bands],
-            peak_decay_rate: 0.95,
+            smoothed_values: vec![SILENCE_DB; num_bands],
+            peak_values: vec![SILENCE_DB; num_bands],
+            peak_decay_db: 0.5,
             smoothing_factor: smoothing_factor.clamp(0.0, 1.0),
         }
     }
@@ -295,8 +597,8 @@ impl SpectrumSmoother {
             if current_value > current_peak {
                 self.peak_values[i] = current_value;
             } else {
-                // Otherwise, decay the peak
-                self.peak_values[i] = current_peak * self.peak_decay_rate;
+                // Otherwise, decay the peak down to silence
+                self.peak_values[i] = (current_peak - self.peak_decay_db).max(SILENCE_DB);
             }
         }
         
@@ -304,7 +606,7 @@ impl SpectrumSmoother {
     }
     
     /// Update peak hold values with decay
-    /// Peaks decay at 0.95 per frame (5% reduction per frame)
+    /// Peaks fall 0.5 dB per frame, no lower than `SILENCE_DB`
     fn update_peaks(&mut self, values: &[f32]) {
         for i in 0..values.len() {
             let current_value = values[i];
@@ -314,8 +616,8 @@ impl SpectrumSmoother {
             if current_value > current_peak {
                 self.peak_values[i] = current_value;
             } else {
-                // Otherwise, decay the peak
-                self.peak_values[i] = current_peak * self.peak_decay_rate;
+                // Otherwise, decay the peak down to silence
+                self.peak_values[i] = (current_peak - self.peak_decay_db).max(SILENCE_DB);
             }
         }
     }
@@ -330,17 +632,20 @@ impl SpectrumSmoother {
         &self.peak_values
     }
     
-    /// Reset all smoothed and peak values to zero
+    /// Reset all smoothed and peak values to silence
     pub fn reset(&mut self) {
-        self.smoothed_values.fill(0.0);
-        self.peak_values.fill(0.0);
+        self.smoothed_values.fill(SILENCE_DB);
+        self.peak_values.fill(SILENCE_DB);
     }
 }
 
 /// Shared spectrum data that is updated by FFT thread and read by render thread
 #[derive(Debug, Clone)]
 pub struct SpectrumData {
+    /// Mono downmix bands, used by the existing visualizer modes
     pub bands: Vec<f32>,
+    pub left: Vec<f32>,
+    pub right: Vec<f32>,
     pub timestamp: Instant,
 }
 
@@ -348,7 +653,9 @@ impl SpectrumData {
     /// Create new spectrum data with the specified number of bands
     pub fn new(num_bands: usize) -> Self {
         SpectrumData {
-            bands: vec![0.0; num_bands],
+            bands: vec![SILENCE_DB; num_bands],
+            left: vec![SILENCE_DB; num_bands],
+            right: vec![SILENCE_DB; num_bands],
             timestamp: Instant::now(),
         }
     }
@@ -362,6 +669,7 @@ pub struct FftProcessor {
     engine: FftEngine,
     binner: FrequencyBinner,
     spectrum_buffer: SharedSpectrum,
+    #[allow(dead_code)]
     sample_rate: u32,
 }
 
@@ -369,12 +677,19 @@ impl FftProcessor {
     /// Create a new FFT processor
     pub fn new(
         sample_source: RingConsumer,
-        num_bands: usize,
+        band_config: BandConfig,
         sample_rate: u32,
-    ) -> (Self, SharedSpectrum) {
-        let engine = FftEngine::new(FFT_SIZE, sample_source);
-        let binner = FrequencyBinner::new(num_bands, FFT_SIZE, sample_rate as f32);
-        let spectrum_buffer = Arc::new(Mutex::new(SpectrumData::new(num_bands)));
+        fft_config: FftConfig,
+        window_control: Arc<WindowControl>,
+        weighting: Weighting,
+        preprocessor: Preprocessor,
+    ) -> Result<(Self, SharedSpectrum), String> {
+        let mut engine = FftEngine::new(fft_config.size, fft_config.hop, sample_source);
+        engine.set_window_control(window_control);
+        engine.set_weighting(weighting, sample_rate as f32);
+        engine.set_preprocessor(preprocessor);
+        let binner = FrequencyBinner::with_config(&band_config, fft_config.size, sample_rate as f32)?;
+        let spectrum_buffer = Arc::new(Mutex::new(SpectrumData::new(binner.num_bands())));
         
         let processor = FftProcessor {
             engine,
@@ -383,7 +698,7 @@ impl FftProcessor {
             sample_rate,
         };
         
-        (processor, spectrum_buffer)
+        Ok((processor, spectrum_buffer))
     }
     
     /// Run the FFT processing loop
@@ -400,27 +715,13 @@ impl FftProcessor {
         loop {
             let loop_start = Instant::now();
             
-            // Process audio block
-            match self.engine.process_block() {
-                Some(fft_magnitudes) => {
-                    // Bin the spectrum into logarithmic bands
-                    let binned_spectrum = self.binner.bin_spectrum(&fft_magnitudes);
-                    
-                    // Update shared spectrum buffer
-                    match self.spectrum_buffer.lock() {
-                        Ok(mut spectrum) => {
-                            spectrum.bands = binned_spectrum;
-                            spectrum.timestamp = Instant::now();
-                        }
-                        Err(e) => {
-                            warn!("Failed to lock spectrum buffer: {}", e);
-                        }
-                    }
-                }
-                None => {
-                    // Not enough samples available, wait a bit
-                    thread::sleep(Duration::from_millis(5));
-                }
+            let hops = self.update();
+            if hops == 0 {
+                // Not enough samples available, wait a bit
+                thread::sleep(Duration::from_millis(5));
+            } else if hops == MAX_HOPS_PER_UPDATE {
+                // More hops are buffered; catch up without pacing
+                continue;
             }
             
             // Sleep to maintain target update rate
@@ -430,19 +731,226 @@ impl FftProcessor {
             }
         }
     }
+    
+    /// Process the buffered hops, up to `MAX_HOPS_PER_UPDATE`, so small hops keep up
+    /// with the input, then publish only the newest spectrum
+    /// Returns the number of hops processed
+    fn update(&mut self) -> usize {
+        let hops = (0..MAX_HOPS_PER_UPDATE).take_while(|_| self.engine.process_block()).count();
+        if hops == 0 {
+            return 0;
+        }
+        
+        let power = self.engine.power();
+        // Bin each spectrum into frequency bands
+        let binned_mono = self.binner.bin_spectrum(&power.mono, power.noise_bandwidth);
+        let binned_left = self.binner.bin_spectrum(&power.left, power.noise_bandwidth);
+        let binned_right = self.binner.bin_spectrum(&power.right, power.noise_bandwidth);
+        
+        // Update shared spectrum buffer
+        match self.spectrum_buffer.lock() {
+            Ok(mut spectrum) => {
+                spectrum.bands = binned_mono;
+                spectrum.left = binned_left;
+                spectrum.right = binned_right;
+                spectrum.timestamp = Instant::now();
+            }
+            Err(e) => {
+                warn!("Failed to lock spectrum buffer: {}", e);
+            }
+        }
+        hops
+    }
 }
 
 /// Spawn FFT processing thread
 pub fn spawn_fft_thread(
     sample_source: RingConsumer,
-    num_bands: usize,
+    band_config: BandConfig,
     sample_rate: u32,
-) -> (std::thread::JoinHandle<()>, SharedSpectrum) {
-    let (processor, spectrum_buffer) = FftProcessor::new(sample_source, num_bands, sample_rate);
+    fft_config: FftConfig,
+    window_control: Arc<WindowControl>,
+    weighting: Weighting,
+    preprocessor: Preprocessor,
+) -> Result<(std::thread::JoinHandle<()>, SharedSpectrum), String> {
+    let (processor, spectrum_buffer) = FftProcessor::new(
+        sample_source,
+        band_config,
+        sample_rate,
+        fft_config,
+        window_control,
+        weighting,
+        preprocessor,
+    )?;
     
     let handle = std::thread::spawn(move || {
         processor.run();
     });
     
-    (handle, spectrum_buffer)
+    Ok((handle, spectrum_buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::create_ring_buffer;
+    use crate::preprocess::PreprocessConfig;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_small_hop_slides_window() {
+        let (producer, consumer) = create_ring_buffer();
+        let samples: Vec<f32> = (0..1024)
+            .map(|n| (2.0 * PI * 1000.0 * n as f32 / 44100.0).sin())
+            .collect();
+        producer.take_writer(44100).unwrap().write(&samples, 1);
+
+        // Each block consumes one 256-frame hop, so four blocks fill the 1024-sample window
+        let mut engine = FftEngine::new(1024, 256, consumer);
+        let mut blocks = 0;
+        while engine.process_block() {
+            blocks += 1;
+        }
+        assert_eq!(blocks, 4);
+
+        let mono = &engine.power().mono;
+        assert_eq!(mono.len(), 513);
+        let peak = (0..mono.len()).max_by(|&a, &b| mono[a].total_cmp(&mono[b])).unwrap();
+        // 1 kHz falls between bins 23 and 24 at 44.1 kHz
+        assert!((23..=24).contains(&peak), "peak at bin {}", peak);
+    }
+
+    #[test]
+    fn test_backlog_is_published_in_capped_batches() {
+        let (producer, consumer) = create_ring_buffer();
+        let hop = 16;
+        let samples: Vec<f32> = (0..(MAX_HOPS_PER_UPDATE + 8) * hop)
+            .map(|n| (2.0 * PI * 1000.0 * n as f32 / 44100.0).sin())
+            .collect();
+        producer.take_writer(44100).unwrap().write(&samples, 1);
+
+        let preprocessor = Preprocessor::new(PreprocessConfig { gain_db: 0.0, agc: None, dc_cutoff_hz: None }, 44100);
+        let window_control = Arc::new(WindowControl::new(WindowKind::Hann, DEFAULT_KAISER_BETA));
+        let fft_config = FftConfig { size: MIN_FFT_SIZE, hop };
+        let (mut processor, spectrum) = FftProcessor::new(consumer, BandConfig::log(16), 44100, fft_config, window_control, Weighting::Z, preprocessor).unwrap();
+
+        // The first update stops at the cap and still publishes
+        assert_eq!(processor.update(), MAX_HOPS_PER_UPDATE);
+        let published = spectrum.lock().unwrap().timestamp;
+        assert!(spectrum.lock().unwrap().bands.iter().any(|&level| level > SILENCE_DB));
+
+        // The rest of the backlog goes out with the next update
+        assert_eq!(processor.update(), 8);
+        assert!(spectrum.lock().unwrap().timestamp > published);
+        assert_eq!(processor.update(), 0);
+    }
+
+    #[test]
+    fn test_full_scale_sine_reads_0_dbfs() {
+        // Full-scale sines over a 1024-sample block; 1 Hz bins at a 1024 Hz sample rate
+        let spectrum = |cycles: f32, kind: WindowKind| {
+            let samples: Vec<f32> = (0..1024).map(|n| (2.0 * PI * cycles * n as f32 / 1024.0).sin()).collect();
+            let (producer, consumer) = create_ring_buffer();
+            producer.take_writer(1024).unwrap().write(&samples, 1);
+            let mut engine = FftEngine::new(1024, 1024, consumer);
+            engine.set_window_control(Arc::new(WindowControl::new(kind, DEFAULT_KAISER_BETA)));
+            assert!(engine.process_block());
+            engine.power().clone()
+        };
+        let config = BandConfig {
+            num_bands: 1,
+            scale: FrequencyScale::Linear,
+            min_freq: 12.5,
+            max_freq: 52.5,
+            ..BandConfig::log(1)
+        };
+        let binner = FrequencyBinner::with_config(&config, 1024, 1024.0).unwrap();
+
+        for kind in WindowKind::ALL {
+            // A sine centered on bin 32 peaks at 0 dBFS
+            let level = power_to_db(spectrum(32.0, kind).mono[32]);
+            assert!(level.abs() < 0.01, "{} reads {} dBFS", kind.name(), level);
+
+            // Band energy reads 0 dBFS even when the sine falls between bins
+            let power = spectrum(32.5, kind);
+            let level = binner.bin_spectrum(&power.mono, power.noise_bandwidth)[0];
+            assert!(level.abs() < 0.1, "{} band reads {} dBFS", kind.name(), level);
+        }
+    }
+
+    #[test]
+    fn test_min_freq_must_be_below_nyquist() {
+        let config = BandConfig { min_freq: 22050.0, max_freq: 30000.0, ..BandConfig::log(16) };
+        let err = FrequencyBinner::with_config(&config, FFT_SIZE, 44100.0).err().unwrap();
+        assert!(err.contains("Nyquist"), "{}", err);
+    }
+
+    #[test]
+    fn test_interpolation_separates_low_bands() {
+        // A spectrum rising by 0.1 dB per bin
+        let power: Vec<f32> = (0..1025).map(|k| 10f32.powf(k as f32 / 100.0)).collect();
+        let lowest_bands = |interpolation| {
+            let config = BandConfig { interpolation, ..BandConfig::log(64) };
+            let binner = FrequencyBinner::with_config(&config, 2048, 44100.0).unwrap();
+            binner.bin_spectrum(&power, 1.0)[..12].to_vec()
+        };
+
+        let plain = lowest_bands(BinInterpolation::None);
+        assert!(plain.windows(2).any(|pair| pair[0] == pair[1]), "{:?}", plain);
+        for interpolation in [BinInterpolation::Overlap, BinInterpolation::Parabolic] {
+            let bands = lowest_bands(interpolation);
+            assert!(bands.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", bands);
+        }
+    }
+
+    #[test]
+    fn test_band_aggregation() {
+        // 1 Hz bins; a band from 2.5 to 5.5 Hz covers bins 3, 4 and 5 exactly
+        let mut power = vec![1e-10; 9];
+        power[3..6].copy_from_slice(&[0.1, 0.1, 0.01]);
+        let band_level = |min_freq, max_freq, aggregation| {
+            let config = BandConfig {
+                num_bands: 1,
+                scale: FrequencyScale::Linear,
+                min_freq,
+                max_freq,
+                interpolation: BinInterpolation::Overlap,
+                aggregation,
+            };
+            FrequencyBinner::with_config(&config, 16, 16.0).unwrap().bin_spectrum(&power, 1.0)[0]
+        };
+        let level = |aggregation| band_level(2.5, 5.5, aggregation);
+
+        // Power adds before conversion: 0.1 + 0.1 + 0.01
+        assert!((level(BandAggregation::Energy) - 10.0 * 0.21f32.log10()).abs() < 1e-3);
+        assert!((level(BandAggregation::Rms) - 10.0 * 0.07f32.log10()).abs() < 1e-3);
+        assert!((level(BandAggregation::Peak) + 10.0).abs() < 1e-3);
+        // Averaging dB values reads lower than the RMS level
+        assert!((level(BandAggregation::DbMean) + 13.333).abs() < 1e-3);
+
+        // A band from 4.6 to 4.8 Hz is narrower than a bin: power is interpolated at its
+        // center, 0.1 + 0.7 * (0.01 - 0.1) = 0.037, and counts as a 0.2-bin-wide bin
+        let sub_bin = |aggregation| band_level(4.6, 4.8, aggregation);
+        assert!((sub_bin(BandAggregation::Energy) - 10.0 * (0.037f32 * 0.2).log10()).abs() < 1e-3);
+        for aggregation in [BandAggregation::Rms, BandAggregation::Peak, BandAggregation::DbMean] {
+            assert!((sub_bin(aggregation) - 10.0 * 0.037f32.log10()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_smoother_peaks_decay_in_db() {
+        let mut smoother = SpectrumSmoother::new(1, 1.0);
+        assert_eq!(smoother.smooth(&[-20.0]), &[-20.0]);
+        assert_eq!(smoother.peak_values(), &[-20.0]);
+
+        // Peaks fall by a fixed step in dB rather than toward 0 dBFS
+        smoother.smooth(&[-60.0]);
+        assert_eq!(smoother.peak_values(), &[-20.5]);
+
+        // and stop at the silence floor
+        for _ in 0..1000 {
+            smoother.smooth(&[SILENCE_DB]);
+        }
+        assert_eq!(smoother.peak_values(), &[SILENCE_DB]);
+    }
 }
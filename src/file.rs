@@ -0,0 +1,457 @@
+// Audio file input module
+
+use log::{debug, error, info, warn};
+use ringbuf::{traits::*, HeapProd, HeapRb};
+use std::fs::File;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::audio::{AudioSource, RealtimePacer, RingProducer, SampleWriter, RING_CHANNELS};
+use crate::playback::{PlaybackControl, PlaybackOutput};
+
+/// Number of frames pushed to the ring buffer per pacing step (~11.6ms at 44.1kHz)
+const FRAMES_PER_CHUNK: usize = 512;
+
+/// Decoded stereo frames queued ahead of the output device (~370ms at 44.1kHz)
+const PLAYBACK_BUFFER_FRAMES: usize = 16384;
+
+/// How long the decoder waits while paused or while the playback queue is full
+const WAIT_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Demuxer and decoder for the selected audio track, moved onto the decoder thread
+struct TrackDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    /// Decoded frames still to discard before the last seek target is reached
+    skip_frames: u64,
+}
+
+impl TrackDecoder {
+    /// Seek to `seconds` and reset the decoder so no stale state leaks across
+    /// The demuxer lands on the packet containing the target; the frames before it
+    /// are discarded as they are decoded
+    fn seek(&mut self, seconds: f64) -> Result<(), String> {
+        let seeked = self.format
+            .seek(SeekMode::Accurate, SeekTo::Time { time: seconds.into(), track_id: Some(self.track_id) })
+            .map_err(|e| format!("Failed to seek to {:.1}s: {}", seconds, e))?;
+        self.skip_frames = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        self.decoder.reset();
+        Ok(())
+    }
+
+    /// Drop the frames that precede the seek target from a decoded buffer
+    fn skip_to_target<'a>(&mut self, samples: &'a [f32], channels: usize) -> &'a [f32] {
+        let skipped = (self.skip_frames as usize).min(samples.len() / channels);
+        self.skip_frames -= skipped as u64;
+        &samples[skipped * channels..]
+    }
+}
+
+/// Output device settings for playing the file while it is visualized
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackSettings {
+    /// Host audio API name (None for the default host)
+    pub host: Option<String>,
+    /// Output device name (None for the default output device)
+    pub device: Option<String>,
+    /// Delay applied to the visuals so they match what is heard
+    pub latency_offset_ms: u32,
+}
+
+/// Where decoded frames go
+enum FrameSink {
+    /// Straight into the ring buffer, paced by the wall clock
+    Paced { writer: SampleWriter, pacer: RealtimePacer },
+    /// Into the queue read by the output device, which then feeds the ring buffer
+    Playback { queue: HeapProd<f32>, stereo: Vec<f32> },
+}
+
+impl FrameSink {
+    /// Deliver interleaved frames, blocking until they are accepted
+    fn write(&mut self, samples: &[f32], channels: usize, control: &PlaybackControl, running: &AtomicBool) {
+        match self {
+            FrameSink::Paced { writer, pacer } => {
+                writer.write(samples, channels);
+                pacer.advance(samples.len() / channels);
+                control.advance_position(samples.len() / channels);
+            }
+            FrameSink::Playback { queue, stereo } => {
+                stereo.clear();
+                stereo.extend(samples.chunks_exact(channels).flat_map(|frame| {
+                    let (left, right) = SampleWriter::frame_to_stereo(frame);
+                    [left, right]
+                }));
+
+                // The output device drains the queue at its own rate; a pending seek
+                // discards the rest of this chunk instead of waiting on a paused device
+                let mut pending = &stereo[..];
+                while !pending.is_empty() && running.load(Ordering::SeqCst) && !control.has_pending_seek() {
+                    let pushed = queue.push_slice(pending);
+                    pending = &pending[pushed..];
+                    if pushed == 0 {
+                        thread::sleep(WAIT_INTERVAL);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Discard frames queued before a seek
+    fn flush(&mut self, control: &PlaybackControl, running: &AtomicBool) {
+        match self {
+            FrameSink::Paced { pacer, .. } => pacer.restart(),
+            FrameSink::Playback { .. } => {
+                let request = control.request_flush();
+                while !control.flush_completed(request) && running.load(Ordering::SeqCst) {
+                    thread::sleep(WAIT_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+/// Audio source that decodes a file (WAV, FLAC, OGG/Vorbis, MP3) and feeds
+/// the ring buffer at real-time pace, optionally playing it through an output device
+pub struct FileSource {
+    path: String,
+    sample_rate: u32,
+    channels: usize,
+    track: Option<TrackDecoder>,
+    playback: Option<PlaybackSettings>,
+    control: Arc<PlaybackControl>,
+    output: Option<PlaybackOutput>,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl FileSource {
+    /// Open an audio file and prepare its first audio track for decoding
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|e| format!("Failed to open input file '{}': {}", path, e))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        // Use the file extension as a hint for the format probe
+        let mut hint = Hint::new();
+        if let Some(extension) = Path::new(path).extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| format!("Unsupported input file '{}': {}", path, e))?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| format!("No audio track found in '{}'", path))?;
+
+        let track_id = track.id;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_else(|| format!("Unknown sample rate in '{}'", path))?;
+        let channels = track
+            .codec_params
+            .channels
+            .map(|c| c.count())
+            .ok_or_else(|| format!("Unknown channel layout in '{}'", path))?;
+        let duration_frames = track.codec_params.n_frames;
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("Unsupported codec in '{}': {}", path, e))?;
+
+        info!("Opened input file: {} (sample_rate={}, channels={})",
+              path, sample_rate, channels);
+
+        Ok(FileSource {
+            path: path.to_string(),
+            sample_rate,
+            channels,
+            track: Some(TrackDecoder { format, decoder, track_id, skip_frames: 0 }),
+            playback: None,
+            control: Arc::new(PlaybackControl::new(sample_rate, duration_frames)),
+            output: None,
+            running: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        })
+    }
+
+    /// Also play the file through an output device, which then clocks the visuals
+    pub fn with_playback(mut self, settings: PlaybackSettings) -> Self {
+        self.playback = Some(settings);
+        self
+    }
+
+    /// Decode packets and hand them to the sink until the track ends or is stopped
+    /// Seek, pause and loop requests from the playback control are applied between packets
+    fn decode_loop(
+        mut track: TrackDecoder,
+        channels: usize,
+        mut sink: FrameSink,
+        control: Arc<PlaybackControl>,
+        running: Arc<AtomicBool>,
+    ) {
+        let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+
+        while running.load(Ordering::SeqCst) {
+            if let Some(target) = control.take_seek() {
+                match track.seek(target) {
+                    Ok(()) => {
+                        sink.flush(&control, &running);
+                        control.set_position_secs(target);
+                    }
+                    Err(e) => warn!("{}", e),
+                }
+            }
+
+            // The output device handles pausing itself; paced playback stops here
+            if let FrameSink::Paced { pacer, .. } = &mut sink {
+                if control.is_paused() {
+                    thread::sleep(WAIT_INTERVAL);
+                    pacer.restart();
+                    continue;
+                }
+            }
+
+            let packet = match track.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    if control.is_looping() {
+                        debug!("Looping input file");
+                        if let Err(e) = track.seek(0.0) {
+                            error!("{}", e);
+                            break;
+                        }
+                        continue;
+                    }
+                    info!("Reached end of input file");
+                    control.mark_finished();
+                    break;
+                }
+                Err(e) => {
+                    error!("Failed to read packet from input file: {}", e);
+                    break;
+                }
+            };
+
+            if packet.track_id() != track.track_id {
+                continue;
+            }
+
+            let decoded = match track.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(SymphoniaError::DecodeError(e)) => {
+                    // Corrupt packets are skipped rather than ending playback
+                    warn!("Skipping undecodable packet: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    error!("Failed to decode input file: {}", e);
+                    break;
+                }
+            };
+
+            // Reallocate the interleaving buffer only when a packet is larger than before
+            let buffer = match sample_buffer.as_mut() {
+                Some(buffer) if buffer.capacity() >= decoded.capacity() * channels => buffer,
+                _ => sample_buffer.insert(SampleBuffer::new(decoded.capacity() as u64, *decoded.spec())),
+            };
+            buffer.copy_interleaved_ref(decoded);
+            let samples = track.skip_to_target(buffer.samples(), channels);
+
+            for chunk in samples.chunks(FRAMES_PER_CHUNK * channels) {
+                if !running.load(Ordering::SeqCst) || control.has_pending_seek() {
+                    break;
+                }
+
+                sink.write(chunk, channels, &control, &running);
+            }
+        }
+
+        debug!("File decoder thread exiting at {:.1}s", control.position_secs());
+    }
+}
+
+impl AudioSource for FileSource {
+    /// Start decoding on a background thread, writing samples to the provided producer
+    fn start(&mut self, producer: RingProducer) -> Result<(), String> {
+        let track = self.track.take()
+            .ok_or_else(|| format!("Input file '{}' has already been played", self.path))?;
+        let writer = producer.take_writer(self.sample_rate)?;
+
+        let sink = match self.playback {
+            Some(ref settings) => {
+                let (queue, frames) = HeapRb::<f32>::new(PLAYBACK_BUFFER_FRAMES * RING_CHANNELS).split();
+                let output = PlaybackOutput::open(
+                    settings.host.as_deref(),
+                    settings.device.as_deref(),
+                    self.sample_rate,
+                    settings.latency_offset_ms,
+                    frames,
+                    writer,
+                    self.control.clone(),
+                )?;
+                self.output = Some(output);
+                FrameSink::Playback { queue, stereo: Vec::new() }
+            }
+            None => FrameSink::Paced { writer, pacer: RealtimePacer::new(self.sample_rate) },
+        };
+
+        let channels = self.channels;
+        let control = self.control.clone();
+        let running = self.running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let worker = thread::Builder::new()
+            .name("file-decoder".to_string())
+            .spawn(move || {
+                Self::decode_loop(track, channels, sink, control, running);
+            })
+            .map_err(|e| format!("Failed to spawn decoder thread: {}", e))?;
+
+        self.worker = Some(worker);
+        info!("File playback started: {}", self.path);
+
+        Ok(())
+    }
+
+    /// Stop decoding and wait for the decoder thread to exit
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("File decoder thread panicked");
+            }
+            info!("File playback stopped");
+        }
+        if let Some(output) = self.output.take() {
+            info!("Closed output device: {}", output.device_name());
+        }
+    }
+
+    /// Get the sample rate of the file
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Get the number of channels in the file
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    /// Get the file path
+    fn name(&self) -> &str {
+        &self.path
+    }
+
+    /// Files can be paused, seeked and looped
+    fn playback(&self) -> Option<Arc<PlaybackControl>> {
+        Some(self.control.clone())
+    }
+}
+
+impl Drop for FileSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{create_ring_buffer, RING_CHANNELS};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    const FRAMES: u32 = 800;
+
+    /// Write an 8 kHz stereo WAV whose left channel ramps up by 10 per frame and right ramps down
+    fn write_ramp_wav(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("termsonic-{}-{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for n in 0..FRAMES as i16 {
+            writer.write_sample(n * 10).unwrap();
+            writer.write_sample(-n * 10).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    /// Pop samples until `count` have arrived or `timeout` passes
+    fn read_samples(consumer: &mut crate::audio::RingConsumer, count: usize, timeout: Duration) -> Vec<f32> {
+        let start = Instant::now();
+        let mut samples = Vec::new();
+        while samples.len() < count && start.elapsed() < timeout {
+            samples.extend(consumer.pop_iter());
+            thread::sleep(Duration::from_millis(5));
+        }
+        samples
+    }
+
+    #[test]
+    fn test_decodes_wav_at_file_rate() {
+        let path = write_ramp_wav("decode");
+        let mut source = FileSource::open(path.to_str().unwrap()).unwrap();
+        assert_eq!((source.sample_rate(), source.channels()), (8000, 2));
+        assert_eq!(source.playback().unwrap().duration_secs(), Some(0.1));
+
+        let (producer, mut consumer) = create_ring_buffer();
+        source.start(producer).unwrap();
+        let mut samples = read_samples(&mut consumer, FRAMES as usize * RING_CHANNELS, Duration::from_secs(2));
+        // Nothing follows the end of the file
+        thread::sleep(Duration::from_millis(50));
+        samples.extend(consumer.pop_iter());
+        source.stop();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(samples.len(), FRAMES as usize * RING_CHANNELS);
+        // Frames arrive interleaved left/right
+        assert_eq!(&samples[..4], &[0.0, 0.0, 10.0 / 32768.0, -10.0 / 32768.0]);
+    }
+
+    #[test]
+    fn test_pause_seek_and_loop() {
+        let path = write_ramp_wav("transport");
+        let mut source = FileSource::open(path.to_str().unwrap()).unwrap();
+        let control = source.playback().unwrap();
+        control.toggle_pause();
+
+        let (producer, mut consumer) = create_ring_buffer();
+        source.start(producer).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(consumer.occupied_len(), 0, "paused source produced frames");
+
+        // Jump to the middle of the track, then play past its end with looping on
+        control.seek_by(0.05);
+        control.toggle_loop();
+        control.toggle_pause();
+        let samples = read_samples(&mut consumer, FRAMES as usize * RING_CHANNELS, Duration::from_secs(2));
+        source.stop();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(samples[0], 400.0 * 10.0 / 32768.0);
+        assert!(samples.len() >= FRAMES as usize * RING_CHANNELS);
+        assert!(!control.is_finished());
+    }
+}
@@ -0,0 +1,336 @@
+// Synthetic signal generator module
+
+use log::{debug, error, info};
+use std::f32::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::audio::{AudioSource, RealtimePacer, RingProducer};
+
+/// Number of frames generated per pacing step
+const FRAMES_PER_CHUNK: usize = 512;
+
+/// Fixed noise seed so generated input is reproducible between runs
+const NOISE_SEED: u32 = 0x1234_5678;
+
+/// Test signal shapes produced by the generator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalKind {
+    Sine,
+    Square,
+    Sawtooth,
+    /// Logarithmic sine sweep from the base frequency to the sweep end frequency
+    Sweep,
+    WhiteNoise,
+    PinkNoise,
+}
+
+impl SignalKind {
+    /// Names accepted by `--generate`
+    pub const NAMES: [&'static str; 6] = ["sine", "square", "sawtooth", "sweep", "white", "pink"];
+
+    /// Parse a signal name as used on the command line
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "sine" => Ok(SignalKind::Sine),
+            "square" => Ok(SignalKind::Square),
+            "sawtooth" | "saw" => Ok(SignalKind::Sawtooth),
+            "sweep" => Ok(SignalKind::Sweep),
+            "white" | "white_noise" => Ok(SignalKind::WhiteNoise),
+            "pink" | "pink_noise" => Ok(SignalKind::PinkNoise),
+            _ => Err(format!(
+                "Invalid signal '{}'. Valid signals are: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Parameters for a generated signal
+#[derive(Debug, Clone)]
+pub struct SignalParams {
+    pub kind: SignalKind,
+    /// Tone frequency in Hz (start frequency for sweeps)
+    pub frequency: f32,
+    /// Peak amplitude (0.0 - 1.0)
+    pub amplitude: f32,
+    /// End frequency of a sweep in Hz
+    pub sweep_end: f32,
+    /// Duration of one sweep in seconds before it restarts
+    pub sweep_seconds: f32,
+    pub sample_rate: u32,
+}
+
+/// Sample-by-sample signal generator
+pub struct SignalGenerator {
+    params: SignalParams,
+    /// Oscillator phase in cycles (0.0 - 1.0)
+    phase: f32,
+    /// Samples elapsed in the current sweep
+    sweep_position: u64,
+    noise_state: u32,
+    /// Paul Kellett pink noise filter state
+    pink_state: [f32; 7],
+}
+
+impl SignalGenerator {
+    /// Create a new generator for the given parameters
+    pub fn new(params: SignalParams) -> Self {
+        SignalGenerator {
+            params,
+            phase: 0.0,
+            sweep_position: 0,
+            noise_state: NOISE_SEED,
+            pink_state: [0.0; 7],
+        }
+    }
+
+    /// Produce the next sample
+    pub fn next_sample(&mut self) -> f32 {
+        let sample_rate = self.params.sample_rate as f32;
+
+        let value = match self.params.kind {
+            SignalKind::Sine => {
+                let value = (2.0 * PI * self.phase).sin();
+                self.advance_phase(self.params.frequency / sample_rate);
+                value
+            }
+            SignalKind::Square => {
+                let value = if self.phase < 0.5 { 1.0 } else { -1.0 };
+                self.advance_phase(self.params.frequency / sample_rate);
+                value
+            }
+            SignalKind::Sawtooth => {
+                let value = 2.0 * self.phase - 1.0;
+                self.advance_phase(self.params.frequency / sample_rate);
+                value
+            }
+            SignalKind::Sweep => {
+                let value = (2.0 * PI * self.phase).sin();
+                let frequency = self.sweep_frequency();
+                self.advance_phase(frequency / sample_rate);
+                value
+            }
+            SignalKind::WhiteNoise => self.white_noise(),
+            SignalKind::PinkNoise => self.pink_noise(),
+        };
+
+        value * self.params.amplitude
+    }
+
+    /// Fill a buffer with consecutive samples
+    pub fn fill(&mut self, output: &mut [f32]) {
+        for sample in output {
+            *sample = self.next_sample();
+        }
+    }
+
+    /// Advance the oscillator phase, wrapping at one cycle
+    fn advance_phase(&mut self, increment: f32) {
+        self.phase = (self.phase + increment).fract();
+    }
+
+    /// Instantaneous frequency of the log sweep
+    /// Formula: f(t) = f_start * (f_end/f_start)^(t/T), restarting every T seconds
+    fn sweep_frequency(&mut self) -> f32 {
+        let sweep_samples = (self.params.sweep_seconds * self.params.sample_rate as f32).max(1.0) as u64;
+        let t = self.sweep_position as f32 / sweep_samples as f32;
+        self.sweep_position = (self.sweep_position + 1) % sweep_samples;
+
+        self.params.frequency * (self.params.sweep_end / self.params.frequency).powf(t)
+    }
+
+    /// Uniform white noise in -1.0..1.0 from a xorshift32 generator
+    fn white_noise(&mut self) -> f32 {
+        let mut x = self.noise_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise_state = x;
+
+        (x as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+
+    /// Pink (1/f) noise using Paul Kellett's refined filter over white noise
+    fn pink_noise(&mut self) -> f32 {
+        let white = self.white_noise();
+        let b = &mut self.pink_state;
+
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+
+        // Scale the filter's ~+20 dB gain back to roughly unit peak
+        (pink * 0.11).clamp(-1.0, 1.0)
+    }
+}
+
+/// Audio source that feeds generated test signals into the ring buffer
+pub struct GeneratorSource {
+    name: String,
+    params: SignalParams,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl GeneratorSource {
+    /// Create a generator source for the given signal parameters
+    pub fn new(params: SignalParams) -> Self {
+        let name = format!("generator:{:?}", params.kind).to_lowercase();
+
+        info!("Signal generator: {:?} at {} Hz, amplitude {}, sample_rate={}",
+              params.kind, params.frequency, params.amplitude, params.sample_rate);
+
+        GeneratorSource {
+            name,
+            params,
+            running: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        }
+    }
+}
+
+impl AudioSource for GeneratorSource {
+    /// Start generating samples on a background thread
+    fn start(&mut self, producer: RingProducer) -> Result<(), String> {
+        let mut generator = SignalGenerator::new(self.params.clone());
+        let sample_rate = self.params.sample_rate;
+        let mut writer = producer.take_writer(sample_rate)?;
+        let running = self.running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let worker = thread::Builder::new()
+            .name("signal-generator".to_string())
+            .spawn(move || {
+                let mut pacer = RealtimePacer::new(sample_rate);
+                let mut chunk = vec![0.0f32; FRAMES_PER_CHUNK];
+
+                while running.load(Ordering::SeqCst) {
+                    generator.fill(&mut chunk);
+                    writer.write(&chunk, 1);
+                    pacer.advance(chunk.len());
+                }
+
+                debug!("Signal generator thread exiting after {} frames", pacer.frames_sent());
+            })
+            .map_err(|e| format!("Failed to spawn signal generator thread: {}", e))?;
+
+        self.worker = Some(worker);
+        info!("Signal generator started");
+
+        Ok(())
+    }
+
+    /// Stop generating samples and wait for the generator thread to exit
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Signal generator thread panicked");
+            }
+            info!("Signal generator stopped");
+        }
+    }
+
+    /// Get the generator sample rate
+    fn sample_rate(&self) -> u32 {
+        self.params.sample_rate
+    }
+
+    /// Generated signals are mono
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    /// Get the generator name (e.g. "generator:sine")
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for GeneratorSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::create_ring_buffer;
+    use crate::fft::{FftEngine, FrequencyBinner, FFT_SIZE};
+
+    fn params(kind: SignalKind, frequency: f32) -> SignalParams {
+        SignalParams {
+            kind,
+            frequency,
+            amplitude: 1.0,
+            sweep_end: 20000.0,
+            sweep_seconds: 1.0,
+            sample_rate: 44100,
+        }
+    }
+
+    #[test]
+    fn test_signal_kind_from_name() {
+        assert_eq!(SignalKind::from_name("sine"), Ok(SignalKind::Sine));
+        assert_eq!(SignalKind::from_name("Pink"), Ok(SignalKind::PinkNoise));
+        assert!(SignalKind::from_name("triangle").is_err());
+    }
+
+    #[test]
+    fn test_signals_stay_within_amplitude() {
+        for name in SignalKind::NAMES {
+            let mut p = params(SignalKind::from_name(name).unwrap(), 440.0);
+            p.amplitude = 0.5;
+            let mut generator = SignalGenerator::new(p);
+
+            let mut buffer = vec![0.0; 44100];
+            generator.fill(&mut buffer);
+            assert!(buffer.iter().all(|s| s.abs() <= 0.5), "{} exceeded amplitude", name);
+        }
+    }
+
+    #[test]
+    fn test_noise_is_reproducible() {
+        let mut a = SignalGenerator::new(params(SignalKind::WhiteNoise, 0.0));
+        let mut b = SignalGenerator::new(params(SignalKind::WhiteNoise, 0.0));
+
+        let mut buffer_a = vec![0.0; 256];
+        let mut buffer_b = vec![0.0; 256];
+        a.fill(&mut buffer_a);
+        b.fill(&mut buffer_b);
+        assert_eq!(buffer_a, buffer_b);
+    }
+
+    #[test]
+    fn test_sine_lights_up_matching_band() {
+        let (producer, consumer) = create_ring_buffer();
+        let mut generator = SignalGenerator::new(params(SignalKind::Sine, 1000.0));
+        let mut samples = vec![0.0; FFT_SIZE];
+        generator.fill(&mut samples);
+        producer.take_writer(44100).unwrap().write(&samples, 1);
+
+        // Two hops fill the 50% overlap window with signal
+        let mut engine = FftEngine::new(FFT_SIZE, FFT_SIZE / 2, consumer);
+        assert!(engine.process_block() && engine.process_block());
+        let power = engine.power();
+
+        let binner = FrequencyBinner::new(32, FFT_SIZE, 44100.0);
+        let bands = binner.bin_spectrum(&power.mono, power.noise_bandwidth);
+        let loudest = (0..bands.len())
+            .max_by(|&a, &b| bands[a].total_cmp(&bands[b]))
+            .unwrap();
+
+        // Bands split 20 Hz-20 kHz logarithmically, so 1 kHz falls in band floor(32 * log1000(50))
+        let expected = (32.0 * (1000.0f32 / 20.0).ln() / 1000.0f32.ln()) as usize;
+        assert_eq!(loudest, expected);
+    }
+}
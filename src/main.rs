@@ -1,17 +1,38 @@
 mod audio;
 mod config;
 mod fft;
+mod file;
+mod generator;
 mod modes;
+mod net;
+mod pcm;
+mod playback;
+mod preprocess;
+mod record;
 mod render;
+mod resample;
+mod scale;
+mod weighting;
+mod window;
 
-use audio::{AudioProcessor, create_ring_buffer};
-use config::CliConfig;
-use fft::{spawn_fft_thread, SpectrumSmoother};
+use audio::{AudioInput, AudioProcessor, AudioSource, CaptureOptions, create_ring_buffer};
+use config::{CliConfig, BYTES_PER_MB};
+use fft::{spawn_fft_thread, BandAggregation, BandConfig, BinInterpolation};
+use file::{FileSource, PlaybackSettings};
+use generator::{GeneratorSource, SignalKind, SignalParams};
 use log::{error, info};
 use modes::{CircularMode, SpectrumBarsMode, WaveformMode};
+use net::{Endpoint, NetworkSink, NetworkSource};
+use pcm::{PcmFormat, PcmSource};
+use ringbuf::traits::Consumer;
+use scale::FrequencyScale;
+use weighting::Weighting;
+use preprocess::{AgcConfig, PreprocessConfig, Preprocessor};
+use record::{Recorder, Rotation};
 use render::{ColorScheme, RenderConfig, RenderLoop, TerminalRenderer};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use window::{WindowControl, WindowKind};
 
 fn main() {
     // Initialize env_logger for logging
@@ -26,11 +47,41 @@ fn main() {
     
     // Handle --list-devices flag with early exit
     if config.list_devices {
+        let devices = match AudioProcessor::list_devices(config.host.as_deref()) {
+            Ok(devices) => devices,
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if config.json {
+            match serde_json::to_string_pretty(&devices) {
+                Ok(json) => println!("{}", json),
+                Err(e) => {
+                    error!("Failed to serialize device list: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+
         println!("Available Audio Input Devices:");
         println!();
-        let devices = AudioProcessor::list_devices();
+        if devices.is_empty() {
+            println!("  No input devices found");
+        }
         for device in devices {
-            println!("{}", device);
+            println!("{}", device.describe());
+        }
+        return;
+    }
+    
+    // Handle --list-hosts flag with early exit
+    if config.list_hosts {
+        println!("Available Audio Hosts:");
+        println!();
+        for (i, host) in audio::available_hosts().iter().enumerate() {
+            println!("  {}. {}{}", i + 1, host, if i == 0 { " (default)" } else { "" });
         }
         return;
     }
@@ -73,7 +124,12 @@ fn main() {
     }));
     
     // Run the application and handle errors
-    if let Err(e) = run_application(config, running) {
+    let result = if config.send.is_some() {
+        run_sender(config, running)
+    } else {
+        run_application(config, running)
+    };
+    if let Err(e) = result {
         error!("Application error: {}", e);
         std::process::exit(1);
     }
@@ -83,33 +139,80 @@ fn main() {
 
 /// Main application logic
 fn run_application(config: CliConfig, running: Arc<AtomicBool>) -> Result<(), String> {
-    // Create AudioProcessor with specified or default device
-    let mut audio_processor = AudioProcessor::new(config.device.as_deref())
-        .map_err(|e| format!("Failed to create audio processor: {}", e))?;
-    
-    let sample_rate = audio_processor.sample_rate();
-    info!("Audio sample rate: {} Hz", sample_rate);
-    
     // Create ring buffer for audio samples
     let (producer, consumer) = create_ring_buffer();
+    producer.set_analysis_rate(config.analysis_rate);
+    
+    // Tee captured frames to a WAV file; the recorder must attach before the source starts
+    let mut recorder = match config.record {
+        Some(ref path) => {
+            let rotation = Rotation {
+                max_bytes: config.record_max_mb.map(|mb| mb * BYTES_PER_MB),
+                max_seconds: config.record_max_seconds,
+            };
+            Some(Recorder::start(path, &producer, rotation)?)
+        }
+        None => None,
+    };
+    
+    // Open the configured audio source and start feeding the ring buffer
+    let capture_options = CaptureOptions {
+        host: config.host.clone(),
+        sample_rate: config.sample_rate,
+        buffer_frames: config.buffer_frames,
+        channels: config.channels,
+    };
+    let audio_source = create_audio_source(&config, &capture_options, &running)?;
+    let mut audio_input = AudioInput::new(audio_source, producer, capture_options);
+    
+    audio_input.start()
+        .map_err(|e| format!("Failed to start audio source '{}': {}", audio_input.source().name(), e))?;
     
-    // Start audio capture
-    audio_processor.start(producer)
-        .map_err(|e| format!("Failed to start audio capture: {}", e))?;
+    info!("Audio source '{}' started successfully", audio_input.source().name());
     
-    info!("Audio capture started successfully");
+    // The FFT runs at the analysis rate when resampling, otherwise at the source rate
+    let sample_rate = audio_input.ring_sample_rate();
+    info!("Audio sample rate: {} Hz (analysis at {} Hz)", audio_input.source().sample_rate(), sample_rate);
+    info!("Audio source channels: {}", audio_input.source().channels());
     
     // Determine number of frequency bands based on terminal width
     let (term_width, _) = crossterm::terminal::size()
         .map_err(|e| format!("Failed to get terminal size: {}", e))?;
     let num_bands = (term_width as usize).clamp(32, 64);
+    let band_config = BandConfig {
+        num_bands,
+        scale: FrequencyScale::from_name(&config.scale)?,
+        min_freq: config.min_freq,
+        max_freq: config.max_freq,
+        interpolation: BinInterpolation::from_name(&config.interpolation)?,
+        aggregation: BandAggregation::from_name(&config.aggregation)?,
+    };
     
-    info!("Using {} frequency bands", num_bands);
+    if band_config.scale.has_fixed_bands() {
+        info!("Using standard {} bands", band_config.scale.name());
+    } else {
+        info!("Using {} {} frequency bands", num_bands, band_config.scale.name());
+    }
     
-    // Spawn FFT processing thread with ring buffer consumer
-    let (fft_handle, spectrum_buffer) = spawn_fft_thread(consumer, num_bands, sample_rate);
+    // Gain, AGC and DC blocking run on the FFT thread before analysis
+    let preprocessor = Preprocessor::new(preprocess_config(&config), sample_rate);
+    let preprocess_status = preprocessor.status();
     
-    info!("FFT processing thread started");
+    // Spawn FFT processing thread with ring buffer consumer
+    let fft_config = config.fft_config();
+    let window_control = Arc::new(WindowControl::new(WindowKind::from_name(&config.window)?, config.kaiser_beta));
+    let weighting = Weighting::from_name(&config.weighting)?;
+    let (fft_handle, spectrum_buffer) = spawn_fft_thread(
+        consumer,
+        band_config,
+        sample_rate,
+        fft_config,
+        window_control.clone(),
+        weighting,
+        preprocessor,
+    )?;
+    
+    info!("FFT processing thread started (size {}, hop {})", fft_config.size, fft_config.hop);
     
     // Parse color scheme from CLI config
     let color_names = config.parse_colors();
@@ -121,6 +224,8 @@ fn run_application(config: CliConfig, running: Arc<AtomicBool>) -> Result<(), St
         sensitivity: config.sensitivity,
         color_scheme,
         show_peaks: true,
+        floor_db: config.floor_db,
+        ceiling_db: config.ceiling_db,
     };
     
     // Initialize selected visualizer mode based on CLI config
@@ -140,7 +245,10 @@ fn run_application(config: CliConfig, running: Arc<AtomicBool>) -> Result<(), St
         .map_err(|e| format!("Failed to create terminal renderer: {}", e))?;
     
     // Create render loop
-    let mut render_loop = RenderLoop::new(renderer, spectrum_buffer, mode, 60);
+    let mut render_loop = RenderLoop::new(renderer, spectrum_buffer, mode, audio_input, 60, running)
+        .with_preprocess_status(preprocess_status)
+        .with_window_control(window_control)
+        .with_weighting(weighting);
     
     // Start main render loop on main thread
     info!("Starting render loop");
@@ -151,9 +259,14 @@ fn run_application(config: CliConfig, running: Arc<AtomicBool>) -> Result<(), St
     // Cleanup sequence
     info!("Initiating cleanup sequence");
     
-    // Stop audio capture
-    audio_processor.stop();
-    info!("Audio capture stopped");
+    // Stop audio source
+    render_loop.audio_input_mut().stop();
+    info!("Audio source stopped");
+    
+    // Finalize the recording so its WAV header matches the data written
+    if let Some(ref mut recorder) = recorder {
+        recorder.stop();
+    }
     
     // Note: FFT thread will be terminated when the process exits
     // In a production app, we'd send a signal to gracefully stop it
@@ -172,3 +285,109 @@ fn run_application(config: CliConfig, running: Arc<AtomicBool>) -> Result<(), St
     
     Ok(())
 }
+
+/// Headless mode: capture from the configured source and stream it to a --listen peer
+fn run_sender(config: CliConfig, running: Arc<AtomicBool>) -> Result<(), String> {
+    let endpoint = Endpoint::parse(config.send.as_deref().unwrap_or_default())?;
+    let format = PcmFormat::from_name(&config.net_format)?;
+    
+    let (producer, mut consumer) = create_ring_buffer();
+    producer.set_analysis_rate(config.analysis_rate);
+    
+    let capture_options = CaptureOptions {
+        host: config.host.clone(),
+        sample_rate: config.sample_rate,
+        buffer_frames: config.buffer_frames,
+        channels: config.channels,
+    };
+    let audio_source = create_audio_source(&config, &capture_options, &running)?;
+    let mut audio_input = AudioInput::new(audio_source, producer, capture_options);
+    
+    audio_input.start()
+        .map_err(|e| format!("Failed to start audio source '{}': {}", audio_input.source().name(), e))?;
+    info!("Sending audio from '{}' to {} ({:?})", audio_input.source().name(), endpoint, format);
+    
+    let mut sink = NetworkSink::new(endpoint, format);
+    let mut frames = vec![0.0f32; sink.frames_per_message() * audio::RING_CHANNELS];
+    
+    while running.load(Ordering::SeqCst) {
+        audio_input.source_mut().poll();
+        
+        let read = consumer.pop_slice(&mut frames);
+        if read == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            continue;
+        }
+        sink.send(&frames[..read], audio_input.ring_sample_rate());
+    }
+    
+    audio_input.stop();
+    info!("Audio source stopped");
+    
+    Ok(())
+}
+
+/// Build the preprocessing settings from the CLI configuration
+fn preprocess_config(config: &CliConfig) -> PreprocessConfig {
+    PreprocessConfig {
+        gain_db: config.gain_db,
+        agc: config.agc.then_some(AgcConfig {
+            target_db: config.agc_target_db,
+            attack_ms: config.agc_attack_ms,
+            release_ms: config.agc_release_ms,
+            max_gain_db: config.agc_max_gain_db,
+        }),
+        dc_cutoff_hz: (config.dc_cutoff_hz > 0.0).then_some(config.dc_cutoff_hz),
+    }
+}
+
+/// Create the audio source selected by the CLI configuration
+fn create_audio_source(
+    config: &CliConfig,
+    capture_options: &CaptureOptions,
+    running: &AtomicBool,
+) -> Result<Box<dyn AudioSource>, String> {
+    if let Some(ref path) = config.input {
+        let mut source = FileSource::open(path)
+            .map_err(|e| format!("Failed to open input file: {}", e))?;
+        if config.play {
+            source = source.with_playback(PlaybackSettings {
+                host: config.host.clone(),
+                device: config.output_device.clone(),
+                latency_offset_ms: config.latency_offset_ms,
+            });
+        }
+        return Ok(Box::new(source));
+    }
+    
+    if let Some(ref path) = config.pcm {
+        let format = PcmFormat::from_name(&config.pcm_format)?;
+        let source = PcmSource::new(path, format, config.pcm_rate, config.pcm_channels)?;
+        return Ok(Box::new(source));
+    }
+    
+    if let Some(ref listen) = config.listen {
+        // The first message sets the sample rate the analysis is built for
+        let mut source = NetworkSource::bind(&Endpoint::parse(listen)?)?;
+        source.wait_for_stream(running)?;
+        return Ok(Box::new(source));
+    }
+    
+    if let Some(ref signal) = config.generate {
+        let params = SignalParams {
+            kind: SignalKind::from_name(signal)?,
+            frequency: config.frequency,
+            amplitude: config.amplitude,
+            sweep_end: config.sweep_end,
+            sweep_seconds: config.sweep_seconds,
+            sample_rate: config.generate_rate,
+        };
+        return Ok(Box::new(GeneratorSource::new(params)));
+    }
+    
+    // Create AudioProcessor with specified or default device
+    let processor = AudioProcessor::new(config.device.as_deref(), capture_options)
+        .map_err(|e| format!("Failed to create audio processor: {}", e))?;
+    
+    Ok(Box::new(processor))
+}
@@ -1,6 +1,6 @@
 // Visualizer modes module
 
-use crate::render::{Canvas, Cell, ColorScheme, RenderConfig, VisualizerMode};
+use crate::render::{Canvas, Cell, RenderConfig};
 use crossterm::style::Color;
 use std::collections::VecDeque;
 
@@ -19,18 +19,18 @@ impl SpectrumBarsMode {
     /// Unicode block characters for rendering bars (from lowest to highest)
     const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
     
-    /// Map a magnitude value (in dB) to a bar height
-    /// Returns height in characters (0 to canvas height)
-    fn magnitude_to_height(magnitude: f32, max_height: usize) -> usize {
-        // Magnitude is in dB, typically ranging from -60 to 0
-        // Normalize to 0.0 - 1.0 range
-        let normalized = ((magnitude + 60.0) / 60.0).clamp(0.0, 1.0);
+    /// Map a magnitude value (in dBFS) to a bar height
+    /// Returns height in characters (0 to canvas height); the ceiling fills the canvas
+    fn magnitude_to_height(magnitude: f32, max_height: usize, config: &RenderConfig) -> usize {
+        // Normalize to 0.0 - 1.0 across the configured display range
+        let normalized = config.normalize_db(magnitude);
         
-        // Scale to canvas height
-        (normalized * max_height as f32) as usize
+        // Scale to canvas height, rounding so a level at the ceiling reaches the top row
+        (normalized * max_height as f32).round() as usize
     }
     
     /// Get the appropriate block character for a given position in the bar
+    #[allow(dead_code)]
     fn get_block_char(position: usize, height: usize, max_height: usize) -> char {
         if position >= max_height - height {
             // We're in the filled part of the bar
@@ -67,7 +67,7 @@ impl VisualizerMode for SpectrumBarsMode {
             let color = config.color_scheme.get_color(i, num_bars);
             
             // Calculate bar height
-            let bar_height = Self::magnitude_to_height(magnitude, height);
+            let bar_height = Self::magnitude_to_height(magnitude, height, config);
             
             // Draw the bar from bottom to top
             for y in 0..height {
@@ -109,7 +109,9 @@ impl VisualizerMode for SpectrumBarsMode {
 
 /// Waveform mode - displays horizontal scrolling waveform
 pub struct WaveformMode {
+    #[allow(dead_code)]
     history: VecDeque<f32>,
+    #[allow(dead_code)]
     max_history: usize,
 }
 
@@ -138,6 +140,7 @@ impl WaveformMode {
     }
     
     /// Map amplitude to vertical position on canvas
+    #[allow(dead_code)]
     fn amplitude_to_y(amplitude: f32, height: usize) -> usize {
         let normalized = amplitude.clamp(0.0, 1.0);
         let y = height as f32 * (1.0 - normalized) / 2.0 + height as f32 / 4.0;
@@ -154,8 +157,9 @@ impl VisualizerMode for WaveformMode {
             return;
         }
         
-        // Calculate current amplitude
-        let amplitude = Self::calculate_rms(spectrum);
+        // Calculate current amplitude, placed within the display range in dBFS
+        let rms_db = 20.0 * Self::calculate_rms(spectrum).max(1e-10).log10();
+        let amplitude = config.normalize_db(rms_db);
         
         // Update history (mutable borrow through interior mutability pattern)
         // Since we can't mutate self in render, we'll work with a local copy
@@ -167,8 +171,6 @@ impl VisualizerMode for WaveformMode {
             canvas.set_cell(x, center_y, Cell::new('─', Color::DarkGrey));
         }
         
-        // Draw amplitude indicator
-        let amp_y = Self::amplitude_to_y(amplitude, height);
         let amp_color = config.color_scheme.get_color(0, 1);
         
         // Draw a simple waveform representation
@@ -290,7 +292,7 @@ impl VisualizerMode for CircularMode {
             let color = config.color_scheme.get_color(i, num_spokes);
             
             // Calculate spoke length based on magnitude
-            let normalized_mag = ((magnitude + 60.0) / 60.0).clamp(0.0, 1.0);
+            let normalized_mag = config.normalize_db(magnitude);
             let spoke_length = normalized_mag * max_radius;
             
             // Draw the spoke
@@ -340,14 +342,20 @@ mod tests {
     #[test]
     fn test_spectrum_bars_magnitude_to_height() {
         // Test magnitude to height conversion
-        let height = SpectrumBarsMode::magnitude_to_height(-60.0, 10);
+        let config = RenderConfig::default();
+        let height = SpectrumBarsMode::magnitude_to_height(-60.0, 10, &config);
         assert_eq!(height, 0);
         
-        let height = SpectrumBarsMode::magnitude_to_height(0.0, 10);
+        let height = SpectrumBarsMode::magnitude_to_height(0.0, 10, &config);
         assert_eq!(height, 10);
         
-        let height = SpectrumBarsMode::magnitude_to_height(-30.0, 10);
+        let height = SpectrumBarsMode::magnitude_to_height(-30.0, 10, &config);
         assert_eq!(height, 5);
+        
+        // A custom range moves the top and bottom of the display
+        let config = RenderConfig { floor_db: -100.0, ceiling_db: -20.0, ..RenderConfig::default() };
+        assert_eq!(SpectrumBarsMode::magnitude_to_height(-20.0, 10, &config), 10);
+        assert_eq!(SpectrumBarsMode::magnitude_to_height(-60.0, 10, &config), 5);
     }
     
     #[test]
@@ -0,0 +1,632 @@
+// Network PCM streaming module (receive with --listen, transmit with --send)
+
+use log::{debug, error, info, warn};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::audio::{AudioSource, RingProducer, SampleWriter, RING_CHANNELS};
+use crate::pcm::PcmFormat;
+
+/// Marks the start of every message
+const MAGIC: [u8; 4] = *b"TSNP";
+
+/// Wire format version
+const VERSION: u8 = 1;
+
+/// Size of the message header in bytes
+const HEADER_LEN: usize = 16;
+
+/// Largest payload the sender puts in one message, small enough for a single UDP datagram
+const MAX_SEND_PAYLOAD: usize = 1024;
+
+/// Largest payload accepted from a peer
+const MAX_RECEIVE_PAYLOAD: usize = 65507 - HEADER_LEN;
+
+/// How long blocking socket calls wait before checking for shutdown
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Delay between attempts to reach an unavailable TCP peer
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Transport used for a network stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Udp,
+    Tcp,
+}
+
+/// Network address in the form "udp://host:port" or "tcp://host:port"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub protocol: Protocol,
+    pub address: String,
+}
+
+impl Endpoint {
+    /// Parse an endpoint as used on the command line
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (scheme, address) = url
+            .split_once("://")
+            .ok_or_else(|| format!("Invalid endpoint '{}'. Expected udp://host:port or tcp://host:port", url))?;
+
+        let protocol = match scheme.to_lowercase().as_str() {
+            "udp" => Protocol::Udp,
+            "tcp" => Protocol::Tcp,
+            _ => return Err(format!("Invalid protocol '{}'. Valid protocols are: udp, tcp", scheme)),
+        };
+
+        let port = address.rsplit_once(':').map(|(_, port)| port);
+        if port.and_then(|p| p.parse::<u16>().ok()).is_none() {
+            return Err(format!("Endpoint '{}' needs a port number", url));
+        }
+
+        Ok(Endpoint {
+            protocol,
+            address: address.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scheme = match self.protocol {
+            Protocol::Udp => "udp",
+            Protocol::Tcp => "tcp",
+        };
+        write!(f, "{}://{}", scheme, self.address)
+    }
+}
+
+/// Header sent in front of every block of interleaved samples
+///
+/// Layout (little-endian): magic "TSNP", version u8, format u8, channels u16,
+/// sample rate u32, payload length u32
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub format: PcmFormat,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub payload_len: u32,
+}
+
+impl PacketHeader {
+    /// Append the encoded header to `output`
+    fn encode(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&MAGIC);
+        output.push(VERSION);
+        output.push(self.format.code());
+        output.extend_from_slice(&self.channels.to_le_bytes());
+        output.extend_from_slice(&self.sample_rate.to_le_bytes());
+        output.extend_from_slice(&self.payload_len.to_le_bytes());
+    }
+
+    /// Parse and validate a header from the first `HEADER_LEN` bytes
+    fn decode(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN {
+            return Err(format!("Message too short for a header ({} bytes)", bytes.len()));
+        }
+        if bytes[..4] != MAGIC {
+            return Err("Message does not start with the stream magic".to_string());
+        }
+        if bytes[4] != VERSION {
+            return Err(format!("Unsupported stream version {}", bytes[4]));
+        }
+
+        let header = PacketHeader {
+            format: PcmFormat::from_code(bytes[5])?,
+            channels: u16::from_le_bytes([bytes[6], bytes[7]]),
+            sample_rate: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            payload_len: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        };
+
+        if header.channels == 0 || header.sample_rate == 0 {
+            return Err("Stream header has zero channels or sample rate".to_string());
+        }
+        if header.payload_len as usize > MAX_RECEIVE_PAYLOAD {
+            return Err(format!("Payload of {} bytes is too large", header.payload_len));
+        }
+        let frame_bytes = header.format.bytes_per_sample() * header.channels as usize;
+        // `usize::is_multiple_of` would need Rust 1.87
+        #[allow(clippy::manual_is_multiple_of)]
+        if header.payload_len as usize % frame_bytes != 0 {
+            return Err("Payload does not hold a whole number of frames".to_string());
+        }
+
+        Ok(header)
+    }
+}
+
+/// Bound socket a receiver reads messages from
+enum Receiver {
+    Udp { socket: UdpSocket, datagram: Vec<u8> },
+    Tcp { listener: TcpListener, stream: Option<TcpStream> },
+}
+
+impl Receiver {
+    /// Bind the endpoint for receiving
+    fn bind(endpoint: &Endpoint) -> Result<Self, String> {
+        let bind_error = |e: io::Error| format!("Failed to listen on {}: {}", endpoint, e);
+
+        match endpoint.protocol {
+            Protocol::Udp => {
+                let socket = UdpSocket::bind(&endpoint.address).map_err(bind_error)?;
+                socket.set_read_timeout(Some(POLL_INTERVAL)).map_err(bind_error)?;
+                Ok(Receiver::Udp { socket, datagram: vec![0; HEADER_LEN + MAX_RECEIVE_PAYLOAD] })
+            }
+            Protocol::Tcp => {
+                let listener = TcpListener::bind(&endpoint.address).map_err(bind_error)?;
+                listener.set_nonblocking(true).map_err(bind_error)?;
+                Ok(Receiver::Tcp { listener, stream: None })
+            }
+        }
+    }
+
+    /// Address the socket is actually bound to
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Receiver::Udp { socket, .. } => socket.local_addr(),
+            Receiver::Tcp { listener, .. } => listener.local_addr(),
+        }
+    }
+
+    /// Wait up to about one poll interval for the next message, storing its samples in `payload`
+    /// Returns None when nothing arrived; malformed messages are logged and skipped
+    fn receive(&mut self, payload: &mut Vec<u8>, running: &AtomicBool) -> Option<PacketHeader> {
+        match self {
+            Receiver::Udp { socket, datagram } => {
+                let length = match socket.recv(datagram) {
+                    Ok(length) => length,
+                    Err(e) if is_timeout(&e) => return None,
+                    Err(e) => {
+                        warn!("Failed to receive datagram: {}", e);
+                        return None;
+                    }
+                };
+
+                let header = match PacketHeader::decode(&datagram[..length]) {
+                    Ok(header) => header,
+                    Err(e) => {
+                        debug!("Ignoring datagram: {}", e);
+                        return None;
+                    }
+                };
+                let end = HEADER_LEN + header.payload_len as usize;
+                if end != length {
+                    debug!("Ignoring datagram with {} bytes instead of {}", length, end);
+                    return None;
+                }
+
+                payload.clear();
+                payload.extend_from_slice(&datagram[HEADER_LEN..end]);
+                Some(header)
+            }
+            Receiver::Tcp { listener, stream } => {
+                let Some(connection) = stream.as_mut() else {
+                    *stream = Self::accept(listener);
+                    return None;
+                };
+
+                match Self::read_message(connection, payload, running) {
+                    Ok(header) => Some(header),
+                    Err(e) => {
+                        if e.kind() == io::ErrorKind::UnexpectedEof {
+                            info!("Network peer disconnected; waiting for a new connection");
+                        } else if e.kind() != io::ErrorKind::Interrupted {
+                            warn!("Dropping network connection: {}", e);
+                        }
+                        *stream = None;
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Accept a pending TCP connection, if any
+    fn accept(listener: &TcpListener) -> Option<TcpStream> {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                info!("Network peer connected: {}", peer);
+                let configured = stream
+                    .set_nonblocking(false)
+                    .and_then(|_| stream.set_read_timeout(Some(POLL_INTERVAL)));
+                match configured {
+                    Ok(()) => Some(stream),
+                    Err(e) => {
+                        warn!("Failed to configure connection from {}: {}", peer, e);
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                if !is_timeout(&e) {
+                    warn!("Failed to accept connection: {}", e);
+                }
+                thread::sleep(POLL_INTERVAL);
+                None
+            }
+        }
+    }
+
+    /// Read one framed message from a TCP stream
+    fn read_message(stream: &mut TcpStream, payload: &mut Vec<u8>, running: &AtomicBool) -> io::Result<PacketHeader> {
+        let mut header_bytes = [0u8; HEADER_LEN];
+        read_full(stream, &mut header_bytes, running)?;
+        let header = PacketHeader::decode(&header_bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        payload.resize(header.payload_len as usize, 0);
+        read_full(stream, payload, running)?;
+        Ok(header)
+    }
+}
+
+/// Fill `buffer` from a stream with a read timeout, checking for shutdown between reads
+fn read_full(stream: &mut TcpStream, buffer: &mut [u8], running: &AtomicBool) -> io::Result<()> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        if !running.load(Ordering::SeqCst) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        match stream.read(&mut buffer[filled..]) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => filled += read,
+            Err(e) if is_timeout(&e) || e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Whether an I/O error only means a timeout or non-blocking call expired
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+/// Audio source that receives PCM streamed by `--send` on another machine
+pub struct NetworkSource {
+    name: String,
+    receiver: Option<Receiver>,
+    /// Stream parameters from the first message, read before start
+    header: Option<PacketHeader>,
+    /// Samples of the first message, delivered once started
+    first_payload: Vec<u8>,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl NetworkSource {
+    /// Bind the endpoint; call `wait_for_stream` before starting
+    pub fn bind(endpoint: &Endpoint) -> Result<Self, String> {
+        let receiver = Receiver::bind(endpoint)?;
+        // Name the source after the bound address, which resolves host names and port 0
+        let bound = Endpoint {
+            protocol: endpoint.protocol,
+            address: receiver.local_addr()
+                .map_err(|e| format!("Failed to read local address of {}: {}", endpoint, e))?
+                .to_string(),
+        };
+        info!("Listening for network audio on {}", bound);
+
+        Ok(NetworkSource {
+            name: bound.to_string(),
+            receiver: Some(receiver),
+            header: None,
+            first_payload: Vec::new(),
+            running: Arc::new(AtomicBool::new(true)),
+            worker: None,
+        })
+    }
+
+    /// Block until the first message arrives, which fixes the stream's sample rate
+    /// Returns an error if `keep_waiting` is cleared first (e.g. by Ctrl+C)
+    pub fn wait_for_stream(&mut self, keep_waiting: &AtomicBool) -> Result<(), String> {
+        let receiver = self.receiver
+            .as_mut()
+            .ok_or_else(|| "Network source has already started".to_string())?;
+
+        info!("Waiting for a network stream on {}...", self.name);
+        let mut payload = Vec::new();
+        while keep_waiting.load(Ordering::SeqCst) {
+            if let Some(header) = receiver.receive(&mut payload, keep_waiting) {
+                info!("Receiving network stream: {:?}, sample_rate={}, channels={}",
+                      header.format, header.sample_rate, header.channels);
+                self.header = Some(header);
+                self.first_payload = payload;
+                return Ok(());
+            }
+        }
+
+        Err(format!("Stopped waiting for a stream on {}", self.name))
+    }
+
+    /// Receive messages and write their samples until stopped
+    fn receive_loop(
+        mut receiver: Receiver,
+        expected: PacketHeader,
+        first_payload: Vec<u8>,
+        mut writer: SampleWriter,
+        running: Arc<AtomicBool>,
+    ) {
+        let mut samples = Vec::new();
+        let mut payload = first_payload;
+        let mut header = Some(expected);
+        let mut rate_warned = false;
+        let mut frames_received: u64 = 0;
+
+        while running.load(Ordering::SeqCst) {
+            if let Some(current) = header {
+                // The analysis was set up for the first message's rate
+                if current.sample_rate == expected.sample_rate {
+                    current.format.decode(&payload, &mut samples);
+                    writer.write(&samples, current.channels as usize);
+                    frames_received += (samples.len() / current.channels as usize) as u64;
+                } else if !rate_warned {
+                    warn!("Network stream changed to {} Hz; ignoring data until it returns to {} Hz",
+                          current.sample_rate, expected.sample_rate);
+                    rate_warned = true;
+                }
+            }
+
+            header = receiver.receive(&mut payload, &running);
+        }
+
+        debug!("Network receiver thread exiting after {} frames", frames_received);
+    }
+}
+
+impl AudioSource for NetworkSource {
+    /// Start receiving on a background thread
+    fn start(&mut self, producer: RingProducer) -> Result<(), String> {
+        let header = self.header
+            .ok_or_else(|| format!("No stream has been received on {}", self.name))?;
+        let receiver = self.receiver
+            .take()
+            .ok_or_else(|| "Network source has already started".to_string())?;
+        let writer = producer.take_writer(header.sample_rate)?;
+        let payload = std::mem::take(&mut self.first_payload);
+
+        let running = self.running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let worker = thread::Builder::new()
+            .name("network-receiver".to_string())
+            .spawn(move || {
+                Self::receive_loop(receiver, header, payload, writer, running);
+            })
+            .map_err(|e| format!("Failed to spawn network receiver thread: {}", e))?;
+
+        self.worker = Some(worker);
+        info!("Network input started: {}", self.name);
+
+        Ok(())
+    }
+
+    /// Stop receiving and wait for the receiver thread to exit
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Network receiver thread panicked");
+            }
+            info!("Network input stopped");
+        }
+    }
+
+    /// Sample rate announced by the sender
+    fn sample_rate(&self) -> u32 {
+        self.header.map(|header| header.sample_rate).unwrap_or(0)
+    }
+
+    /// Channel count announced by the sender
+    fn channels(&self) -> u16 {
+        self.header.map(|header| header.channels).unwrap_or(0)
+    }
+
+    /// Get the endpoint (e.g. "udp://0.0.0.0:9000")
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for NetworkSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Connected socket a sender writes messages to
+enum Transmitter {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+/// Streams stereo frames to a `--listen` peer
+pub struct NetworkSink {
+    endpoint: Endpoint,
+    format: PcmFormat,
+    transmitter: Option<Transmitter>,
+    last_attempt: Option<Instant>,
+    message: Vec<u8>,
+}
+
+impl NetworkSink {
+    /// Create a sink sending in `format`; the connection is opened on first use
+    pub fn new(endpoint: Endpoint, format: PcmFormat) -> Self {
+        NetworkSink {
+            endpoint,
+            format,
+            transmitter: None,
+            last_attempt: None,
+            message: Vec::with_capacity(HEADER_LEN + MAX_SEND_PAYLOAD),
+        }
+    }
+
+    /// Number of stereo frames that fit in one message
+    pub fn frames_per_message(&self) -> usize {
+        MAX_SEND_PAYLOAD / (self.format.bytes_per_sample() * RING_CHANNELS)
+    }
+
+    /// Send interleaved stereo frames, split into messages
+    /// Frames are dropped while the peer is unreachable
+    pub fn send(&mut self, frames: &[f32], sample_rate: u32) {
+        for chunk in frames.chunks(self.frames_per_message() * RING_CHANNELS) {
+            self.message.clear();
+            let header = PacketHeader {
+                format: self.format,
+                channels: RING_CHANNELS as u16,
+                sample_rate,
+                payload_len: (chunk.len() * self.format.bytes_per_sample()) as u32,
+            };
+            header.encode(&mut self.message);
+            self.format.encode(chunk, &mut self.message);
+
+            if !self.ensure_connected() {
+                return;
+            }
+            let Some(transmitter) = self.transmitter.as_mut() else {
+                return;
+            };
+
+            let result = match transmitter {
+                Transmitter::Udp(socket) => socket.send(&self.message).map(|_| ()),
+                Transmitter::Tcp(stream) => stream.write_all(&self.message),
+            };
+
+            if let Err(e) = result {
+                match self.endpoint.protocol {
+                    // UDP reports an absent listener on localhost; keep sending regardless
+                    Protocol::Udp => debug!("Failed to send datagram: {}", e),
+                    Protocol::Tcp => {
+                        warn!("Lost connection to {}: {}", self.endpoint, e);
+                        self.transmitter = None;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a connection is open, reconnecting at most once per `RECONNECT_INTERVAL`
+    fn ensure_connected(&mut self) -> bool {
+        if self.transmitter.is_none() {
+            if self.last_attempt.is_some_and(|t| t.elapsed() < RECONNECT_INTERVAL) {
+                return false;
+            }
+            self.last_attempt = Some(Instant::now());
+
+            match self.connect() {
+                Ok(transmitter) => {
+                    info!("Streaming to {}", self.endpoint);
+                    self.transmitter = Some(transmitter);
+                }
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        self.transmitter.is_some()
+    }
+
+    fn connect(&self) -> Result<Transmitter, String> {
+        let connect_error = |e: io::Error| format!("Failed to connect to {}: {}", self.endpoint, e);
+
+        match self.endpoint.protocol {
+            Protocol::Udp => {
+                let socket = UdpSocket::bind("0.0.0.0:0").map_err(connect_error)?;
+                socket.connect(&self.endpoint.address).map_err(connect_error)?;
+                Ok(Transmitter::Udp(socket))
+            }
+            Protocol::Tcp => {
+                let stream = TcpStream::connect(&self.endpoint.address).map_err(connect_error)?;
+                stream.set_nodelay(true).map_err(connect_error)?;
+                Ok(Transmitter::Tcp(stream))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::create_ring_buffer;
+    use ringbuf::traits::{Consumer, Observer};
+
+    #[test]
+    fn test_endpoint_parse() {
+        let endpoint = Endpoint::parse("udp://127.0.0.1:9000").unwrap();
+        assert_eq!(endpoint.protocol, Protocol::Udp);
+        assert_eq!(endpoint.address, "127.0.0.1:9000");
+        assert_eq!(endpoint.to_string(), "udp://127.0.0.1:9000");
+
+        assert!(Endpoint::parse("127.0.0.1:9000").is_err());
+        assert!(Endpoint::parse("http://127.0.0.1:9000").is_err());
+        assert!(Endpoint::parse("tcp://localhost").is_err());
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        let header = PacketHeader {
+            format: PcmFormat::F32Le,
+            channels: 2,
+            sample_rate: 48000,
+            payload_len: 64,
+        };
+        let mut bytes = Vec::new();
+        header.encode(&mut bytes);
+        assert_eq!(bytes.len(), HEADER_LEN);
+        assert_eq!(PacketHeader::decode(&bytes), Ok(header));
+
+        bytes[0] = b'X';
+        assert!(PacketHeader::decode(&bytes).is_err());
+    }
+
+    /// Stream a block of frames over localhost and check it arrives in the ring buffer
+    fn stream_over_localhost(protocol: Protocol) {
+        let bind = Endpoint { protocol, address: "127.0.0.1:0".to_string() };
+        let mut source = NetworkSource::bind(&bind).unwrap();
+        let address = Endpoint::parse(source.name()).unwrap().address;
+
+        let sending = Arc::new(AtomicBool::new(true));
+        let sender_running = sending.clone();
+        let sender = thread::spawn(move || {
+            let mut sink = NetworkSink::new(Endpoint { protocol, address }, PcmFormat::F32Le);
+            while sender_running.load(Ordering::SeqCst) {
+                sink.send(&[0.25, -0.25, 0.5, -0.5], 22050);
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let waiting = AtomicBool::new(true);
+        source.wait_for_stream(&waiting).unwrap();
+        assert_eq!(source.sample_rate(), 22050);
+        assert_eq!(source.channels(), 2);
+
+        let (producer, mut consumer) = create_ring_buffer();
+        source.start(producer).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while consumer.occupied_len() < 4 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        source.stop();
+        sending.store(false, Ordering::SeqCst);
+        sender.join().unwrap();
+
+        let mut samples = [0.0; 4];
+        assert_eq!(consumer.pop_slice(&mut samples), 4);
+        assert_eq!(samples, [0.25, -0.25, 0.5, -0.5]);
+    }
+
+    #[test]
+    fn test_udp_stream_over_localhost() {
+        stream_over_localhost(Protocol::Udp);
+    }
+
+    #[test]
+    fn test_tcp_stream_over_localhost() {
+        stream_over_localhost(Protocol::Tcp);
+    }
+}
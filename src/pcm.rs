@@ -0,0 +1,281 @@
+// Raw PCM input module (stdin or named pipe)
+
+use log::{debug, error, info, warn};
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::audio::{AudioSource, RealtimePacer, RingProducer, SampleWriter};
+
+/// Number of frames read from the pipe per iteration
+const FRAMES_PER_READ: usize = 512;
+
+/// Path value that selects standard input
+pub const STDIN_PATH: &str = "-";
+
+/// Sample encodings accepted on the PCM input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcmFormat {
+    /// Signed 16-bit little-endian integers
+    S16Le,
+    /// 32-bit little-endian IEEE floats
+    F32Le,
+}
+
+impl PcmFormat {
+    /// Names accepted by `--pcm-format`
+    pub const NAMES: [&'static str; 2] = ["s16le", "f32le"];
+
+    /// Parse a format name as used on the command line
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "s16le" => Ok(PcmFormat::S16Le),
+            "f32le" => Ok(PcmFormat::F32Le),
+            _ => Err(format!(
+                "Invalid PCM format '{}'. Valid formats are: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Identifier used for this format in network stream headers
+    pub fn code(self) -> u8 {
+        match self {
+            PcmFormat::S16Le => 1,
+            PcmFormat::F32Le => 2,
+        }
+    }
+
+    /// Look up a format by its network header identifier
+    pub fn from_code(code: u8) -> Result<Self, String> {
+        match code {
+            1 => Ok(PcmFormat::S16Le),
+            2 => Ok(PcmFormat::F32Le),
+            _ => Err(format!("Unknown PCM format code {}", code)),
+        }
+    }
+
+    /// Size of one sample in bytes
+    pub(crate) fn bytes_per_sample(self) -> usize {
+        match self {
+            PcmFormat::S16Le => 2,
+            PcmFormat::F32Le => 4,
+        }
+    }
+
+    /// Decode little-endian sample bytes into normalized f32 samples
+    pub(crate) fn decode(self, bytes: &[u8], output: &mut Vec<f32>) {
+        output.clear();
+        match self {
+            PcmFormat::S16Le => output.extend(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0),
+            ),
+            PcmFormat::F32Le => output.extend(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            ),
+        }
+    }
+
+    /// Encode normalized f32 samples as little-endian bytes, appending to `output`
+    pub(crate) fn encode(self, samples: &[f32], output: &mut Vec<u8>) {
+        match self {
+            PcmFormat::S16Le => output.extend(
+                samples
+                    .iter()
+                    .flat_map(|&s| ((s.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes()),
+            ),
+            PcmFormat::F32Le => output.extend(samples.iter().flat_map(|s| s.to_le_bytes())),
+        }
+    }
+}
+
+/// Audio source that reads interleaved raw PCM from stdin or a FIFO
+pub struct PcmSource {
+    path: String,
+    format: PcmFormat,
+    sample_rate: u32,
+    channels: u16,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl PcmSource {
+    /// Create a PCM source reading from `path` ("-" for stdin)
+    /// The input is opened on the reader thread because opening a FIFO
+    /// blocks until a writer connects
+    pub fn new(path: &str, format: PcmFormat, sample_rate: u32, channels: u16) -> Result<Self, String> {
+        if sample_rate == 0 {
+            return Err("PCM sample rate must be greater than zero".to_string());
+        }
+        if channels == 0 {
+            return Err("PCM channel count must be greater than zero".to_string());
+        }
+
+        info!("PCM input: {} ({:?}, sample_rate={}, channels={})",
+              path, format, sample_rate, channels);
+
+        Ok(PcmSource {
+            path: path.to_string(),
+            format,
+            sample_rate,
+            channels,
+            running: Arc::new(AtomicBool::new(false)),
+            worker: None,
+        })
+    }
+
+    /// Open the configured input for reading
+    fn open_reader(path: &str) -> io::Result<Box<dyn Read>> {
+        if path == STDIN_PATH {
+            Ok(Box::new(io::stdin()))
+        } else {
+            Ok(Box::new(File::open(path)?))
+        }
+    }
+
+    /// Read PCM frames until EOF or stop, pushing them to the ring buffer
+    fn read_loop(
+        path: String,
+        format: PcmFormat,
+        sample_rate: u32,
+        channels: usize,
+        mut writer: SampleWriter,
+        running: Arc<AtomicBool>,
+    ) {
+        let mut reader = match Self::open_reader(&path) {
+            Ok(reader) => reader,
+            Err(e) => {
+                error!("Failed to open PCM input '{}': {}", path, e);
+                return;
+            }
+        };
+
+        let frame_bytes = format.bytes_per_sample() * channels;
+        let mut bytes = vec![0u8; FRAMES_PER_READ * frame_bytes];
+        let mut samples = Vec::with_capacity(FRAMES_PER_READ * channels);
+        // Number of bytes at the start of `bytes` carried over from a partial frame
+        let mut pending = 0;
+        let mut pacer = RealtimePacer::new(sample_rate);
+
+        while running.load(Ordering::SeqCst) {
+            let read = match reader.read(&mut bytes[pending..]) {
+                Ok(0) => {
+                    info!("PCM input reached end of stream");
+                    break;
+                }
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    error!("Failed to read PCM input: {}", e);
+                    break;
+                }
+            };
+
+            // Only whole frames are decoded; the remainder waits for the next read
+            let available = pending + read;
+            let whole = available - available % frame_bytes;
+            format.decode(&bytes[..whole], &mut samples);
+            bytes.copy_within(whole..available, 0);
+            pending = available - whole;
+
+            writer.write(&samples, channels);
+            pacer.advance(samples.len() / channels);
+        }
+
+        if pending > 0 {
+            warn!("PCM input ended with {} bytes of an incomplete frame", pending);
+        }
+        debug!("PCM reader thread exiting after {} frames", pacer.frames_sent());
+    }
+}
+
+impl AudioSource for PcmSource {
+    /// Start reading PCM on a background thread
+    fn start(&mut self, producer: RingProducer) -> Result<(), String> {
+        let path = self.path.clone();
+        let format = self.format;
+        let sample_rate = self.sample_rate;
+        let channels = self.channels as usize;
+        let writer = producer.take_writer(self.sample_rate)?;
+        let running = self.running.clone();
+        running.store(true, Ordering::SeqCst);
+
+        let worker = thread::Builder::new()
+            .name("pcm-reader".to_string())
+            .spawn(move || {
+                Self::read_loop(path, format, sample_rate, channels, writer, running);
+            })
+            .map_err(|e| format!("Failed to spawn PCM reader thread: {}", e))?;
+
+        self.worker = Some(worker);
+        info!("PCM input started: {}", self.path);
+
+        Ok(())
+    }
+
+    /// Stop reading PCM
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            // The reader may be blocked in read() on an idle pipe, so only join a
+            // thread that has already finished; otherwise it exits with the process
+            if worker.is_finished() && worker.join().is_err() {
+                error!("PCM reader thread panicked");
+            }
+            info!("PCM input stopped");
+        }
+    }
+
+    /// Get the configured sample rate
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Get the configured channel count
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Get the input path ("-" for stdin)
+    fn name(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for PcmSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcm_format_from_name() {
+        assert_eq!(PcmFormat::from_name("s16le"), Ok(PcmFormat::S16Le));
+        assert_eq!(PcmFormat::from_name("F32LE"), Ok(PcmFormat::F32Le));
+        assert!(PcmFormat::from_name("u8").is_err());
+    }
+
+    #[test]
+    fn test_pcm_decode_normalizes_samples() {
+        let mut samples = Vec::new();
+
+        let bytes: Vec<u8> = [i16::MIN, 0, 16384].iter().flat_map(|s| s.to_le_bytes()).collect();
+        PcmFormat::S16Le.decode(&bytes, &mut samples);
+        assert_eq!(samples, vec![-1.0, 0.0, 0.5]);
+
+        let bytes: Vec<u8> = [0.25f32, -0.75].iter().flat_map(|s| s.to_le_bytes()).collect();
+        PcmFormat::F32Le.decode(&bytes, &mut samples);
+        assert_eq!(samples, vec![0.25, -0.75]);
+    }
+}
@@ -0,0 +1,438 @@
+// Audio output module for playing file input alongside the visualization
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Device, FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use log::{error, info, warn};
+use ringbuf::{traits::*, HeapCons, HeapRb};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::audio::{select_host, SampleWriter, RING_CHANNELS};
+
+/// Stereo frames moved from the playback ring per step inside the output callback
+const SCRATCH_FRAMES: usize = 512;
+
+/// Largest accepted latency offset in milliseconds
+pub const MAX_LATENCY_OFFSET_MS: u32 = 2000;
+
+/// Transport state shared between the render loop, the decoder and the output callback
+pub struct PlaybackControl {
+    sample_rate: u32,
+    /// Track length in frames, if the container reports it
+    duration_frames: Option<u64>,
+    paused: AtomicBool,
+    looping: AtomicBool,
+    finished: AtomicBool,
+    /// Frames delivered since the start of playback (keeps counting across loops)
+    position_frames: AtomicU64,
+    /// Absolute seek target in seconds, taken by the decoder thread
+    seek_target: Mutex<Option<f64>>,
+    /// Flush handshake: the decoder bumps `flush_requested` after a seek and
+    /// waits until the output callback has emptied its ring and caught up
+    flush_requested: AtomicU64,
+    flush_done: AtomicU64,
+}
+
+impl PlaybackControl {
+    /// Create the control for a track at `sample_rate` with an optional known length
+    pub fn new(sample_rate: u32, duration_frames: Option<u64>) -> Self {
+        PlaybackControl {
+            sample_rate,
+            duration_frames,
+            paused: AtomicBool::new(false),
+            looping: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            position_frames: AtomicU64::new(0),
+            seek_target: Mutex::new(None),
+            flush_requested: AtomicU64::new(0),
+            flush_done: AtomicU64::new(0),
+        }
+    }
+
+    /// Toggle between paused and playing
+    pub fn toggle_pause(&self) {
+        self.paused.fetch_xor(true, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Toggle restarting the track when it ends
+    pub fn toggle_loop(&self) {
+        self.looping.fetch_xor(true, Ordering::SeqCst);
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping.load(Ordering::SeqCst)
+    }
+
+    /// Whether the track reached its end without looping
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn mark_finished(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+    }
+
+    /// Current position within the track in seconds
+    pub fn position_secs(&self) -> f64 {
+        let mut frames = self.position_frames.load(Ordering::Relaxed);
+        if let Some(duration) = self.duration_frames.filter(|&d| d > 0) {
+            frames %= duration;
+        }
+        frames as f64 / self.sample_rate as f64
+    }
+
+    /// Track length in seconds, if known
+    pub fn duration_secs(&self) -> Option<f64> {
+        self.duration_frames.map(|frames| frames as f64 / self.sample_rate as f64)
+    }
+
+    /// Request a seek relative to the current position, clamped to the track
+    pub fn seek_by(&self, seconds: f64) {
+        let mut target = (self.position_secs() + seconds).max(0.0);
+        if let Some(duration) = self.duration_secs() {
+            target = target.min(duration);
+        }
+
+        if let Ok(mut seek_target) = self.seek_target.lock() {
+            *seek_target = Some(target);
+        }
+        self.finished.store(false, Ordering::SeqCst);
+    }
+
+    pub(crate) fn has_pending_seek(&self) -> bool {
+        self.seek_target.lock().map(|t| t.is_some()).unwrap_or(false)
+    }
+
+    pub(crate) fn take_seek(&self) -> Option<f64> {
+        self.seek_target.lock().ok().and_then(|mut t| t.take())
+    }
+
+    pub(crate) fn advance_position(&self, frames: usize) {
+        self.position_frames.fetch_add(frames as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_position_secs(&self, seconds: f64) {
+        let frames = (seconds * self.sample_rate as f64) as u64;
+        self.position_frames.store(frames, Ordering::Relaxed);
+    }
+
+    /// Ask the output callback to discard queued frames; returns the request id
+    pub(crate) fn request_flush(&self) -> u64 {
+        self.flush_requested.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub(crate) fn flush_completed(&self, request: u64) -> bool {
+        self.flush_done.load(Ordering::SeqCst) >= request
+    }
+
+    /// Run `flush` if a flush was requested since the last call
+    fn service_flush(&self, flush: impl FnOnce()) {
+        let requested = self.flush_requested.load(Ordering::SeqCst);
+        if self.flush_done.load(Ordering::SeqCst) != requested {
+            flush();
+            self.flush_done.store(requested, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Output stream that plays decoded frames and forwards them to the visualizer
+///
+/// The output callback is the only clock: each frame handed to the device is
+/// also written to the ring buffer, delayed by the latency offset so the bars
+/// line up with what comes out of the speakers.
+pub struct PlaybackOutput {
+    stream: Stream,
+    device_name: String,
+}
+
+impl PlaybackOutput {
+    /// Open an output device at the track's sample rate and start playing from `frames`
+    pub fn open(
+        host_name: Option<&str>,
+        device_name: Option<&str>,
+        sample_rate: u32,
+        latency_offset_ms: u32,
+        frames: HeapCons<f32>,
+        writer: SampleWriter,
+        control: Arc<PlaybackControl>,
+    ) -> Result<Self, String> {
+        let device = Self::find_output_device(host_name, device_name)?;
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+        let (config, sample_format) = Self::select_config(&device, sample_rate)?;
+
+        let offset_frames = (sample_rate as u64 * latency_offset_ms as u64 / 1000) as usize;
+        let callback = OutputCallback::new(frames, writer, control, config.channels as usize, offset_frames);
+
+        let stream = match sample_format {
+            SampleFormat::F32 => Self::build_stream::<f32>(&device, &config, callback),
+            SampleFormat::F64 => Self::build_stream::<f64>(&device, &config, callback),
+            SampleFormat::I8 => Self::build_stream::<i8>(&device, &config, callback),
+            SampleFormat::I16 => Self::build_stream::<i16>(&device, &config, callback),
+            SampleFormat::I32 => Self::build_stream::<i32>(&device, &config, callback),
+            SampleFormat::U8 => Self::build_stream::<u8>(&device, &config, callback),
+            SampleFormat::U16 => Self::build_stream::<u16>(&device, &config, callback),
+            SampleFormat::U32 => Self::build_stream::<u32>(&device, &config, callback),
+            other => return Err(format!("Unsupported output sample format: {}", other)),
+        }?;
+
+        stream
+            .play()
+            .map_err(|e| format!("Failed to start output stream: {}", e))?;
+
+        info!("Playing through output device: {} (sample_rate={}, channels={}, format={}, offset={}ms)",
+              device_name, sample_rate, config.channels, sample_format, latency_offset_ms);
+
+        Ok(PlaybackOutput { stream, device_name })
+    }
+
+    /// Name of the device being played to
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Find an output device of a host by name, or the host's default output device
+    fn find_output_device(host_name: Option<&str>, name: Option<&str>) -> Result<Device, String> {
+        let host = select_host(host_name)?;
+
+        let Some(name) = name else {
+            return host
+                .default_output_device()
+                .ok_or_else(|| "No default output device available".to_string());
+        };
+
+        let devices: Vec<Device> = host
+            .output_devices()
+            .map_err(|e| format!("Failed to enumerate output devices: {}", e))?
+            .collect();
+
+        // Prefer an exact match, then fall back to a substring match
+        let names: Vec<String> = devices
+            .iter()
+            .map(|d| d.name().unwrap_or_default())
+            .collect();
+        let index = names
+            .iter()
+            .position(|n| n == name)
+            .or_else(|| names.iter().position(|n| n.contains(name)))
+            .ok_or_else(|| format!("Output device '{}' not found", name))?;
+
+        Ok(devices.into_iter().nth(index).expect("index comes from the same list"))
+    }
+
+    /// Pick an output configuration running at the track's sample rate
+    /// Stereo (or more) layouts are preferred so both channels are heard
+    fn select_config(device: &Device, sample_rate: u32) -> Result<(StreamConfig, SampleFormat), String> {
+        let rate = cpal::SampleRate(sample_rate);
+        let mut ranges: Vec<_> = device
+            .supported_output_configs()
+            .map_err(|e| format!("Failed to query output configurations: {}", e))?
+            .filter(|range| range.min_sample_rate() <= rate && rate <= range.max_sample_rate())
+            .collect();
+
+        ranges.sort_by_key(|range| {
+            let channels = range.channels() as usize;
+            (channels < RING_CHANNELS, channels.abs_diff(RING_CHANNELS), range.sample_format() != SampleFormat::F32)
+        });
+
+        let range = ranges
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("Output device does not support {} Hz", sample_rate))?;
+        let supported = range.with_sample_rate(rate);
+
+        Ok((supported.config(), supported.sample_format()))
+    }
+
+    fn build_stream<T>(device: &Device, config: &StreamConfig, mut callback: OutputCallback) -> Result<Stream, String>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], _: &cpal::OutputCallbackInfo| callback.fill(data),
+                |err| error!("Audio output stream error: {}", err),
+                None,
+            )
+            .map_err(|e| format!("Failed to build output stream: {}", e))
+    }
+}
+
+impl Drop for PlaybackOutput {
+    fn drop(&mut self) {
+        if let Err(e) = self.stream.pause() {
+            warn!("Failed to pause output stream: {}", e);
+        }
+    }
+}
+
+/// State moved into the real-time output callback; all buffers are allocated up front
+struct OutputCallback {
+    frames: HeapCons<f32>,
+    writer: SampleWriter,
+    control: Arc<PlaybackControl>,
+    device_channels: usize,
+    /// Fixed-length delay line between the device and the visualizer
+    delay: HeapRb<f32>,
+    offset_frames: usize,
+    scratch: Vec<f32>,
+}
+
+impl OutputCallback {
+    fn new(
+        frames: HeapCons<f32>,
+        writer: SampleWriter,
+        control: Arc<PlaybackControl>,
+        device_channels: usize,
+        offset_frames: usize,
+    ) -> Self {
+        // Pre-filling with silence keeps the visual feed exactly `offset_frames` behind
+        let mut delay = HeapRb::<f32>::new((offset_frames + SCRATCH_FRAMES) * RING_CHANNELS);
+        delay.push_iter(std::iter::repeat_n(0.0, offset_frames * RING_CHANNELS));
+
+        OutputCallback {
+            frames,
+            writer,
+            control,
+            device_channels,
+            delay,
+            offset_frames,
+            scratch: vec![0.0; SCRATCH_FRAMES * RING_CHANNELS],
+        }
+    }
+
+    /// Fill one device buffer from the playback ring
+    fn fill<T>(&mut self, data: &mut [T])
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        // Frames still in the delay line belong to the old position; restart it with silence
+        let (frames, delay, offset_frames) = (&mut self.frames, &mut self.delay, self.offset_frames);
+        self.control.service_flush(|| {
+            frames.clear();
+            delay.clear();
+            delay.push_iter(std::iter::repeat_n(0.0, offset_frames * RING_CHANNELS));
+        });
+
+        data.fill(T::from_sample(0.0f32));
+        if self.control.is_paused() {
+            return;
+        }
+
+        for output in data.chunks_mut(SCRATCH_FRAMES * self.device_channels) {
+            let wanted = output.len() / self.device_channels;
+            let got = self.frames.pop_slice(&mut self.scratch[..wanted * RING_CHANNELS]) / RING_CHANNELS;
+
+            for (frame, stereo) in output.chunks_mut(self.device_channels).zip(self.scratch.chunks(RING_CHANNELS).take(got)) {
+                Self::write_frame(frame, stereo[0], stereo[1]);
+            }
+
+            if got > 0 {
+                self.control.advance_position(got);
+                self.delay.push_slice(&self.scratch[..got * RING_CHANNELS]);
+                let delayed = self.delay.pop_slice(&mut self.scratch[..got * RING_CHANNELS]);
+                self.writer.write(&self.scratch[..delayed], RING_CHANNELS);
+            }
+            if got < wanted {
+                // Decoder underrun or end of track; the rest of the buffer stays silent
+                break;
+            }
+        }
+    }
+
+    /// Map a stereo frame onto the device's channel layout
+    fn write_frame<T>(frame: &mut [T], left: f32, right: f32)
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        match frame.len() {
+            1 => frame[0] = T::from_sample((left + right) * 0.5),
+            _ => {
+                frame[0] = T::from_sample(left);
+                frame[1] = T::from_sample(right);
+                for extra in &mut frame[2..] {
+                    *extra = T::from_sample(0.0f32);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::create_ring_buffer;
+
+    #[test]
+    fn test_output_feeds_visuals_after_latency_offset() {
+        let (producer, mut visual) = create_ring_buffer();
+        let (mut queue, frames) = HeapRb::<f32>::new(64).split();
+        queue.push_slice(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8]);
+
+        let control = Arc::new(PlaybackControl::new(44100, None));
+        let writer = producer.take_writer(44100).unwrap();
+        let mut callback = OutputCallback::new(frames, writer, control.clone(), 2, 2);
+
+        // The device hears every queued frame right away
+        let mut device = [0.0f32; 8];
+        callback.fill(&mut device);
+        assert_eq!(device, [0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8]);
+        assert_eq!(control.position_secs(), 4.0 / 44100.0);
+
+        // The visuals lag by the two-frame offset
+        let mut samples = [1.0f32; 8];
+        assert_eq!(visual.pop_slice(&mut samples), 8);
+        assert_eq!(samples, [0.0, 0.0, 0.0, 0.0, 0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_flush_discards_queued_and_delayed_frames() {
+        let (producer, mut visual) = create_ring_buffer();
+        let (mut queue, frames) = HeapRb::<f32>::new(64).split();
+        queue.push_slice(&[0.1, 0.2, 0.3, 0.4]);
+
+        let control = Arc::new(PlaybackControl::new(44100, None));
+        let mut callback = OutputCallback::new(frames, producer.take_writer(44100).unwrap(), control.clone(), 2, 2);
+
+        // Two frames reach the device; the visuals only see the leading silence
+        let mut device = [0.0f32; 4];
+        callback.fill(&mut device);
+        let mut samples = [1.0f32; 8];
+        assert_eq!(visual.pop_slice(&mut samples), 4);
+        assert_eq!(&samples[..4], &[0.0; 4]);
+
+        // A seek flushes both the queue and the delay line
+        queue.push_slice(&[0.9, 0.9]);
+        let request = control.request_flush();
+        callback.fill(&mut device);
+        assert!(control.flush_completed(request));
+        assert_eq!(device, [0.0; 4]);
+
+        // Frames from the new position play at once, and the visuals get fresh silence
+        // instead of the frames delayed before the seek
+        queue.push_slice(&[0.5, 0.6, 0.7, 0.8]);
+        callback.fill(&mut device);
+        assert_eq!(device, [0.5, 0.6, 0.7, 0.8]);
+        assert_eq!(visual.pop_slice(&mut samples), 4);
+        assert_eq!(&samples[..4], &[0.0; 4]);
+    }
+
+    #[test]
+    fn test_paused_output_is_silent() {
+        let (producer, _visual) = create_ring_buffer();
+        let (mut queue, frames) = HeapRb::<f32>::new(64).split();
+        queue.push_slice(&[0.5, 0.5]);
+
+        let control = Arc::new(PlaybackControl::new(44100, None));
+        control.toggle_pause();
+        let mut callback = OutputCallback::new(frames, producer.take_writer(44100).unwrap(), control, 1, 0);
+
+        let mut device = [1.0f32; 4];
+        callback.fill(&mut device);
+        assert_eq!(device, [0.0; 4]);
+    }
+}
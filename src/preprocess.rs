@@ -0,0 +1,234 @@
+// Signal preprocessing between capture and FFT analysis
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::audio::RING_CHANNELS;
+
+/// Preprocessing settings from the command line
+#[derive(Debug, Clone)]
+pub struct PreprocessConfig {
+    /// Fixed input gain in dB
+    pub gain_db: f32,
+    /// Automatic gain control, applied after the fixed gain
+    pub agc: Option<AgcConfig>,
+    /// Cutoff of the DC-blocking high-pass filter in Hz (None disables it)
+    pub dc_cutoff_hz: Option<f32>,
+}
+
+/// Automatic gain control settings
+#[derive(Debug, Clone)]
+pub struct AgcConfig {
+    /// Peak level the AGC steers towards in dBFS
+    pub target_db: f32,
+    /// Time constant for reacting to louder input
+    pub attack_ms: f32,
+    /// Time constant for recovering after the input gets quieter
+    pub release_ms: f32,
+    /// Upper limit on the gain the AGC may apply in dB
+    pub max_gain_db: f32,
+}
+
+/// Live preprocessing state read by the HUD
+pub struct PreprocessStatus {
+    config: PreprocessConfig,
+    /// Current AGC gain in dB, stored as f32 bits
+    agc_gain_db: AtomicU32,
+}
+
+impl PreprocessStatus {
+    /// Current AGC gain in dB
+    pub fn agc_gain_db(&self) -> f32 {
+        f32::from_bits(self.agc_gain_db.load(Ordering::Relaxed))
+    }
+
+    /// One-line summary of the active stages, e.g. "gain +6.0 dB | AGC +3.2 dB | DC 10 Hz"
+    pub fn hud_text(&self) -> String {
+        let mut parts = vec![format!("gain {:+.1} dB", self.config.gain_db)];
+        if self.config.agc.is_some() {
+            parts.push(format!("AGC {:+.1} dB", self.agc_gain_db()));
+        }
+        match self.config.dc_cutoff_hz {
+            Some(cutoff) => parts.push(format!("DC {} Hz", cutoff)),
+            None => parts.push("DC off".to_string()),
+        }
+        parts.join(" | ")
+    }
+}
+
+/// Applies DC blocking, fixed gain and AGC to interleaved stereo frames
+pub struct Preprocessor {
+    gain: f32,
+    agc: Option<Agc>,
+    dc_blockers: Option<[DcBlocker; RING_CHANNELS]>,
+    status: Arc<PreprocessStatus>,
+}
+
+impl Preprocessor {
+    /// Create a preprocessor for frames at `sample_rate`
+    pub fn new(config: PreprocessConfig, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate as f32;
+        let agc = config.agc.as_ref().map(|agc| Agc::new(agc, sample_rate));
+        let dc_blockers = config
+            .dc_cutoff_hz
+            .map(|cutoff| std::array::from_fn(|_| DcBlocker::new(cutoff, sample_rate)));
+
+        Preprocessor {
+            gain: db_to_linear(config.gain_db),
+            agc,
+            dc_blockers,
+            status: Arc::new(PreprocessStatus {
+                config,
+                agc_gain_db: AtomicU32::new(0.0f32.to_bits()),
+            }),
+        }
+    }
+
+    /// Shared status for the HUD
+    pub fn status(&self) -> Arc<PreprocessStatus> {
+        self.status.clone()
+    }
+
+    /// Process interleaved stereo frames in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(RING_CHANNELS) {
+            if let Some(blockers) = self.dc_blockers.as_mut() {
+                for (sample, blocker) in frame.iter_mut().zip(blockers.iter_mut()) {
+                    *sample = blocker.process(*sample);
+                }
+            }
+
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+
+            if let Some(agc) = self.agc.as_mut() {
+                // Both channels share one gain so the stereo image is preserved
+                let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+                let gain = agc.process(peak);
+                for sample in frame.iter_mut() {
+                    *sample *= gain;
+                }
+            }
+        }
+
+        if let Some(agc) = self.agc.as_ref() {
+            let gain_db = linear_to_db(agc.gain);
+            self.status.agc_gain_db.store(gain_db.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Peak-following automatic gain control
+struct Agc {
+    target: f32,
+    max_gain: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    envelope: f32,
+    gain: f32,
+}
+
+impl Agc {
+    fn new(config: &AgcConfig, sample_rate: f32) -> Self {
+        Agc {
+            target: db_to_linear(config.target_db),
+            max_gain: db_to_linear(config.max_gain_db),
+            attack_coeff: time_constant_coeff(config.attack_ms, sample_rate),
+            release_coeff: time_constant_coeff(config.release_ms, sample_rate),
+            envelope: 0.0,
+            gain: 1.0,
+        }
+    }
+
+    /// Update the envelope with a frame's peak level and return the gain to apply
+    fn process(&mut self, peak: f32) -> f32 {
+        let coeff = if peak > self.envelope {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope = coeff * self.envelope + (1.0 - coeff) * peak;
+
+        // Silence would drive the gain to infinity, so it is capped at max_gain
+        self.gain = (self.target / self.envelope.max(1e-9)).min(self.max_gain);
+        self.gain
+    }
+}
+
+/// One-pole DC-blocking high-pass filter
+/// Formula: y[n] = x[n] - x[n-1] + R * y[n-1], with R = exp(-2π * fc / fs)
+struct DcBlocker {
+    pole: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl DcBlocker {
+    fn new(cutoff_hz: f32, sample_rate: f32) -> Self {
+        DcBlocker {
+            pole: (-2.0 * std::f32::consts::PI * cutoff_hz / sample_rate).exp(),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = input - self.previous_input + self.pole * self.previous_output;
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+/// Per-sample smoothing coefficient for a time constant in milliseconds
+fn time_constant_coeff(time_ms: f32, sample_rate: f32) -> f32 {
+    (-1.0 / (time_ms / 1000.0 * sample_rate)).exp()
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
+fn linear_to_db(linear: f32) -> f32 {
+    20.0 * linear.max(1e-10).log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PreprocessConfig {
+        PreprocessConfig { gain_db: 0.0, agc: None, dc_cutoff_hz: None }
+    }
+
+    #[test]
+    fn test_fixed_gain() {
+        let mut preprocessor = Preprocessor::new(PreprocessConfig { gain_db: 6.0, ..config() }, 44100);
+        let mut samples = [0.25, -0.25];
+        preprocessor.process(&mut samples);
+        assert!((samples[0] - 0.4988).abs() < 1e-3);
+        assert!((samples[1] + 0.4988).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_dc_blocker_removes_offset() {
+        let mut preprocessor = Preprocessor::new(PreprocessConfig { dc_cutoff_hz: Some(10.0), ..config() }, 44100);
+        let mut samples = vec![0.5; 44100 * RING_CHANNELS];
+        preprocessor.process(&mut samples);
+        assert!(samples[samples.len() - 1].abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_agc_raises_quiet_input_towards_target() {
+        let agc = AgcConfig { target_db: -6.0, attack_ms: 5.0, release_ms: 50.0, max_gain_db: 40.0 };
+        let mut preprocessor = Preprocessor::new(PreprocessConfig { agc: Some(agc), ..config() }, 44100);
+        let mut samples = vec![0.01; 44100 * RING_CHANNELS];
+        preprocessor.process(&mut samples);
+
+        // 0.01 is -40 dBFS, so the AGC settles at +34 dB to reach -6 dBFS
+        let status = preprocessor.status();
+        assert!((status.agc_gain_db() - 34.0).abs() < 0.1, "gain {}", status.agc_gain_db());
+        assert!((samples[samples.len() - 1] - 0.501).abs() < 0.01);
+    }
+}
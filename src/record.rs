@@ -0,0 +1,274 @@
+// WAV recording module
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use log::{debug, error, info, warn};
+use ringbuf::traits::Consumer;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::audio::{RingConsumer, RingProducer, RING_BUFFER_CAPACITY, RING_CHANNELS};
+
+/// Capacity of the recording tee in stereo frames (~1.5s at 44.1kHz)
+const TEE_CAPACITY: usize = RING_BUFFER_CAPACITY * 8;
+
+/// Samples drained from the tee per iteration
+const DRAIN_CHUNK: usize = 4096;
+
+/// How long the writer sleeps when the tee is empty
+const IDLE_SLEEP: Duration = Duration::from_millis(20);
+
+/// Upper bound on the size of the WAV header hound writes
+const WAV_HEADER_BYTES: u64 = 80;
+
+/// Largest data chunk a WAV file can describe with its 32-bit size fields
+pub const MAX_WAV_BYTES: u64 = u32::MAX as u64 - WAV_HEADER_BYTES;
+
+/// When to close the current file and continue in a new one
+#[derive(Debug, Clone, Default)]
+pub struct Rotation {
+    /// Maximum file size in bytes
+    pub max_bytes: Option<u64>,
+    /// Maximum file duration in seconds
+    pub max_seconds: Option<u64>,
+}
+
+/// Background writer that records the stereo frames sent to the ring buffer
+pub struct Recorder {
+    path: PathBuf,
+    running: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    producer: RingProducer,
+}
+
+impl Recorder {
+    /// Attach a tee to the producer and start writing its frames to `path`
+    /// Must be called before the audio source is started
+    pub fn start(path: &str, producer: &RingProducer, rotation: Rotation) -> Result<Self, String> {
+        let consumer = producer.attach_tee(TEE_CAPACITY)?;
+        let sample_rate = producer.sample_rate_handle();
+        let running = Arc::new(AtomicBool::new(true));
+
+        let mut files = RecordingFiles::new(PathBuf::from(path), rotation);
+        let worker_running = running.clone();
+        let worker = thread::Builder::new()
+            .name("wav-recorder".to_string())
+            .spawn(move || files.run(consumer, sample_rate, worker_running))
+            .map_err(|e| format!("Failed to spawn recorder thread: {}", e))?;
+
+        info!("Recording to {}", path);
+
+        Ok(Recorder {
+            path: PathBuf::from(path),
+            running,
+            worker: Some(worker),
+            producer: producer.clone(),
+        })
+    }
+
+    /// Drain the remaining frames, finalize the WAV header and wait for the writer to exit
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Recorder thread panicked");
+            }
+
+            let dropped = self.producer.tee_overruns();
+            if dropped > 0 {
+                warn!("Recording dropped {} samples because the writer fell behind", dropped);
+            }
+            info!("Recording stopped: {}", self.path.display());
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Current output file and the rotation state behind it
+struct RecordingFiles {
+    base_path: PathBuf,
+    rotation: Rotation,
+    writer: Option<WavWriter<BufWriter<File>>>,
+    /// Sample rate of the open file
+    sample_rate: u32,
+    /// Stereo frames written to the open file
+    frames: u64,
+    /// Number of files opened so far
+    index: u32,
+}
+
+impl RecordingFiles {
+    fn new(base_path: PathBuf, rotation: Rotation) -> Self {
+        RecordingFiles {
+            base_path,
+            rotation,
+            writer: None,
+            sample_rate: 0,
+            frames: 0,
+            index: 0,
+        }
+    }
+
+    /// Copy frames from the tee to disk until stopped, then drain what is left
+    fn run(&mut self, mut consumer: RingConsumer, sample_rate: Arc<AtomicU32>, running: Arc<AtomicBool>) {
+        let mut buffer = vec![0.0f32; DRAIN_CHUNK];
+
+        loop {
+            let stopping = !running.load(Ordering::SeqCst);
+
+            // Frames stay queued until the source has reported its sample rate
+            let rate = sample_rate.load(Ordering::Relaxed);
+            if rate == 0 {
+                if stopping {
+                    break;
+                }
+                thread::sleep(IDLE_SLEEP);
+                continue;
+            }
+
+            let read = consumer.pop_slice(&mut buffer);
+            let read = read - read % RING_CHANNELS;
+
+            if read > 0 {
+                if let Err(e) = self.write(&buffer[..read], rate) {
+                    error!("Recording failed: {}", e);
+                    break;
+                }
+            } else if stopping {
+                break;
+            } else {
+                thread::sleep(IDLE_SLEEP);
+            }
+        }
+
+        self.finish();
+        debug!("Recorder thread exiting after {} files", self.index);
+    }
+
+    /// Write interleaved stereo samples, rotating files as needed
+    fn write(&mut self, samples: &[f32], sample_rate: u32) -> Result<(), String> {
+        // A new file is started when the source changes rate so each header stays correct
+        if self.writer.is_some() && sample_rate != self.sample_rate {
+            info!("Sample rate changed to {} Hz; starting a new recording file", sample_rate);
+            self.finish();
+        }
+
+        for frame in samples.chunks_exact(RING_CHANNELS) {
+            if self.writer.is_none() || self.should_rotate() {
+                self.open_next(sample_rate)?;
+            }
+
+            if let Some(writer) = self.writer.as_mut() {
+                for &sample in frame {
+                    writer
+                        .write_sample(sample)
+                        .map_err(|e| format!("Failed to write recording: {}", e))?;
+                }
+            }
+            self.frames += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the open file has reached its size or duration limit
+    fn should_rotate(&self) -> bool {
+        let bytes = (self.frames + 1) * Self::frame_bytes();
+        let max_bytes = self.rotation.max_bytes.unwrap_or(MAX_WAV_BYTES).min(MAX_WAV_BYTES);
+        let too_large = bytes + WAV_HEADER_BYTES > max_bytes;
+        let too_long = self
+            .rotation
+            .max_seconds
+            .is_some_and(|seconds| self.frames >= seconds * self.sample_rate as u64);
+
+        too_large || too_long
+    }
+
+    /// Finalize the current file and open the next one in the sequence
+    fn open_next(&mut self, sample_rate: u32) -> Result<(), String> {
+        self.finish();
+
+        let path = Self::numbered_path(&self.base_path, self.index);
+        let spec = WavSpec {
+            channels: RING_CHANNELS as u16,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let writer = WavWriter::create(&path, spec)
+            .map_err(|e| format!("Failed to create recording '{}': {}", path.display(), e))?;
+
+        info!("Recording file opened: {} ({} Hz)", path.display(), sample_rate);
+        self.writer = Some(writer);
+        self.sample_rate = sample_rate;
+        self.frames = 0;
+        self.index += 1;
+        Ok(())
+    }
+
+    /// Write the final header sizes and close the open file
+    fn finish(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            if let Err(e) = writer.finalize() {
+                error!("Failed to finalize recording: {}", e);
+            }
+        }
+    }
+
+    /// Bytes used by one stereo frame of 32-bit float samples
+    fn frame_bytes() -> u64 {
+        (RING_CHANNELS * std::mem::size_of::<f32>()) as u64
+    }
+
+    /// Path for the n-th file: the base path first, then "name-001.wav", "name-002.wav", ...
+    fn numbered_path(base: &Path, index: u32) -> PathBuf {
+        if index == 0 {
+            return base.to_path_buf();
+        }
+
+        let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+        let extension = base.extension().and_then(|e| e.to_str()).unwrap_or("wav");
+        base.with_file_name(format!("{}-{:03}.{}", stem, index, extension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbered_path() {
+        let base = Path::new("/tmp/capture.wav");
+        assert_eq!(RecordingFiles::numbered_path(base, 0), PathBuf::from("/tmp/capture.wav"));
+        assert_eq!(RecordingFiles::numbered_path(base, 2), PathBuf::from("/tmp/capture-002.wav"));
+    }
+
+    #[test]
+    fn test_rotates_by_duration_with_valid_headers() {
+        let dir = std::env::temp_dir().join(format!("termsonic-record-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("take.wav");
+
+        let rotation = Rotation { max_bytes: None, max_seconds: Some(1) };
+        let mut files = RecordingFiles::new(base.clone(), rotation);
+        files.write(&vec![0.25f32; 150 * RING_CHANNELS], 100).unwrap();
+        files.finish();
+
+        let first = hound::WavReader::open(&base).unwrap();
+        assert_eq!(first.spec().sample_rate, 100);
+        assert_eq!(first.duration(), 100);
+        let second = hound::WavReader::open(dir.join("take-001.wav")).unwrap();
+        assert_eq!(second.duration(), 50);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
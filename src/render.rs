@@ -9,9 +9,27 @@ use crossterm::{
 };
 use log::{debug, error, info, warn};
 use std::io::{self, Stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::fft::SharedSpectrum;
+use crate::audio::{AudioInput, SourceStatus};
+use crate::fft::{SharedSpectrum, SILENCE_DB};
+use crate::preprocess::PreprocessStatus;
+use crate::weighting::Weighting;
+use crate::window::WindowControl;
+
+/// Distance jumped by the seek keys in seconds
+const SEEK_STEP_SECONDS: f64 = 5.0;
+
+/// Minimum time between ring buffer overrun warnings
+const OVERRUN_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default bottom of the display range in dBFS
+pub const DEFAULT_FLOOR_DB: f32 = -60.0;
+
+/// Default top of the display range in dBFS
+pub const DEFAULT_CEILING_DB: f32 = 0.0;
 
 /// Canvas for internal frame buffer representation
 #[derive(Debug, Clone)]
@@ -72,6 +90,7 @@ impl Canvas {
     }
     
     /// Get a cell at the specified position
+    #[allow(dead_code)]
     pub fn get_cell(&self, x: usize, y: usize) -> Option<&Cell> {
         if x < self.width && y < self.height {
             Some(&self.buffer[y][x])
@@ -96,6 +115,19 @@ impl Canvas {
         self.buffer = vec![vec![Cell::empty(); width]; height];
     }
     
+    /// Write a line of text starting at the specified position, clipped to the canvas
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, color: Color) {
+        for (i, ch) in text.chars().enumerate() {
+            self.set_cell(x + i, y, Cell::new(ch, color));
+        }
+    }
+    
+    /// Write a line of text horizontally centered on the specified row
+    pub fn draw_centered_text(&mut self, y: usize, text: &str, color: Color) {
+        let x = self.width.saturating_sub(text.chars().count()) / 2;
+        self.draw_text(x, y, text, color);
+    }
+    
     /// Get a reference to the buffer
     pub fn buffer(&self) -> &Vec<Vec<Cell>> {
         &self.buffer
@@ -108,6 +140,17 @@ pub struct RenderConfig {
     pub sensitivity: f32,
     pub color_scheme: ColorScheme,
     pub show_peaks: bool,
+    /// Level in dBFS drawn at the bottom of the display
+    pub floor_db: f32,
+    /// Level in dBFS drawn at the top of the display
+    pub ceiling_db: f32,
+}
+
+impl RenderConfig {
+    /// Position of a level in dBFS within the display range, from 0.0 (floor) to 1.0 (ceiling)
+    pub fn normalize_db(&self, level: f32) -> f32 {
+        ((level - self.floor_db) / (self.ceiling_db - self.floor_db)).clamp(0.0, 1.0)
+    }
 }
 
 impl Default for RenderConfig {
@@ -116,6 +159,8 @@ impl Default for RenderConfig {
             sensitivity: 1.0,
             color_scheme: ColorScheme::default(),
             show_peaks: true,
+            floor_db: DEFAULT_FLOOR_DB,
+            ceiling_db: DEFAULT_CEILING_DB,
         }
     }
 }
@@ -128,11 +173,13 @@ pub struct ColorScheme {
 
 impl ColorScheme {
     /// Create a new color scheme with the specified colors
+    #[allow(dead_code)]
     pub fn new(colors: Vec<Color>) -> Self {
         ColorScheme { colors }
     }
     
     /// Create a gradient color scheme from a list of colors
+    #[allow(dead_code)]
     pub fn gradient(colors: Vec<Color>) -> Self {
         if colors.is_empty() {
             Self::default()
@@ -220,6 +267,7 @@ impl ColorScheme {
     }
     
     /// Get the list of colors in this scheme
+    #[allow(dead_code)]
     pub fn colors(&self) -> &[Color] {
         &self.colors
     }
@@ -295,6 +343,7 @@ impl TerminalRenderer {
     }
     
     /// Get a reference to the canvas
+    #[allow(dead_code)]
     pub fn canvas(&self) -> &Canvas {
         &self.canvas
     }
@@ -305,6 +354,7 @@ impl TerminalRenderer {
     }
     
     /// Get the render configuration
+    #[allow(dead_code)]
     pub fn config(&self) -> &RenderConfig {
         &self.config
     }
@@ -343,6 +393,7 @@ impl TerminalRenderer {
     }
     
     /// Clear the terminal screen
+    #[allow(dead_code)]
     pub fn clear(&mut self) -> io::Result<()> {
         execute!(
             self.stdout,
@@ -388,12 +439,152 @@ pub trait VisualizerMode: Send {
     fn name(&self) -> &str;
 }
 
+/// Overlay listing input devices so capture can be switched without restarting
+struct DevicePicker {
+    devices: Vec<String>,
+    selected: usize,
+    /// Error from the last switch attempt, shown under the list
+    message: Option<String>,
+}
+
+impl DevicePicker {
+    /// Create a picker for a device list, preselecting the active device
+    fn new(devices: Vec<String>, active_name: &str) -> Self {
+        let selected = devices.iter().position(|d| d == active_name).unwrap_or(0);
+        
+        DevicePicker {
+            devices,
+            selected,
+            message: None,
+        }
+    }
+    
+    /// Move the selection up or down, wrapping around the list
+    fn move_selection(&mut self, down: bool) {
+        if self.devices.is_empty() {
+            return;
+        }
+        let len = self.devices.len();
+        self.selected = if down {
+            (self.selected + 1) % len
+        } else {
+            (self.selected + len - 1) % len
+        };
+    }
+    
+    /// Get the name of the highlighted device
+    fn selected_device(&self) -> Option<&str> {
+        self.devices.get(self.selected).map(String::as_str)
+    }
+    
+    /// Draw the picker as a box in the middle of the canvas
+    fn render(&self, canvas: &mut Canvas) {
+        let title = " Select input device (↑/↓, Enter, Esc) ";
+        let mut lines: Vec<(String, Color)> = if self.devices.is_empty() {
+            vec![("  No input devices found".to_string(), Color::DarkGrey)]
+        } else {
+            self.devices
+                .iter()
+                .enumerate()
+                .map(|(i, name)| {
+                    if i == self.selected {
+                        (format!("> {}", name), Color::Yellow)
+                    } else {
+                        (format!("  {}", name), Color::White)
+                    }
+                })
+                .collect()
+        };
+        if let Some(ref message) = self.message {
+            lines.push((String::new(), Color::White));
+            lines.push((format!("  {}", message), Color::Red));
+        }
+        
+        let content_width = lines
+            .iter()
+            .map(|(text, _)| text.chars().count())
+            .chain(std::iter::once(title.chars().count()))
+            .max()
+            .unwrap_or(0);
+        let box_width = (content_width + 4).min(canvas.width());
+        let box_height = (lines.len() + 2).min(canvas.height());
+        let left = (canvas.width() - box_width) / 2;
+        let top = (canvas.height() - box_height) / 2;
+        
+        // Clear the box area and draw the border
+        for y in top..top + box_height {
+            for x in left..left + box_width {
+                let on_edge = y == top || y == top + box_height - 1 || x == left || x == left + box_width - 1;
+                let ch = if on_edge { '·' } else { ' ' };
+                canvas.set_cell(x, y, Cell::new(ch, Color::DarkGrey));
+            }
+        }
+        canvas.draw_text(left + 2, top, title, Color::Cyan);
+        
+        for (row, (text, color)) in lines.iter().enumerate().take(box_height.saturating_sub(2)) {
+            let clipped: String = text.chars().take(box_width.saturating_sub(4)).collect();
+            canvas.draw_text(left + 2, top + 1 + row, &clipped, *color);
+        }
+    }
+}
+
+/// Rate-limits ring buffer overrun warnings so only new drops are logged
+struct OverrunReporter {
+    /// Overrun count at the last report
+    reported: u64,
+    last_report: Instant,
+}
+
+impl OverrunReporter {
+    fn new(now: Instant) -> Self {
+        OverrunReporter { reported: 0, last_report: now }
+    }
+
+    /// Samples dropped since the last report, at most once per `OVERRUN_REPORT_INTERVAL`
+    fn check(&mut self, overruns: u64, now: Instant) -> Option<u64> {
+        if now.duration_since(self.last_report) < OVERRUN_REPORT_INTERVAL {
+            return None;
+        }
+
+        self.last_report = now;
+        let dropped = overruns.checked_sub(self.reported).filter(|&dropped| dropped > 0)?;
+        self.reported = overruns;
+        Some(dropped)
+    }
+}
+
+/// HUD line for the active preprocessing, window and weighting (None if there is nothing to show)
+fn hud_text(
+    preprocess_status: Option<&PreprocessStatus>,
+    window_control: Option<&WindowControl>,
+    weighting: Option<Weighting>,
+) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(status) = preprocess_status {
+        parts.push(status.hud_text());
+    }
+    if let Some(control) = window_control {
+        parts.push(format!("window {}", control.hud_text()));
+    }
+    if let Some(weighting) = weighting {
+        parts.push(format!("{}-weighted", weighting.name()));
+    }
+    (!parts.is_empty()).then(|| format!(" {} ", parts.join(" | ")))
+}
+
 /// Main rendering loop that runs at 30-60 FPS
 pub struct RenderLoop {
     renderer: TerminalRenderer,
     spectrum_buffer: SharedSpectrum,
     mode: Box<dyn VisualizerMode>,
+    audio_input: AudioInput,
+    device_picker: Option<DevicePicker>,
+    preprocess_status: Option<Arc<PreprocessStatus>>,
+    window_control: Option<Arc<WindowControl>>,
+    weighting: Option<Weighting>,
+    overrun_reporter: OverrunReporter,
     target_fps: u32,
+    running: Arc<AtomicBool>,
 }
 
 impl RenderLoop {
@@ -402,7 +593,9 @@ impl RenderLoop {
         renderer: TerminalRenderer,
         spectrum_buffer: SharedSpectrum,
         mode: Box<dyn VisualizerMode>,
+        audio_input: AudioInput,
         target_fps: u32,
+        running: Arc<AtomicBool>,
     ) -> Self {
         let target_fps = target_fps.clamp(30, 60);
         
@@ -413,10 +606,35 @@ impl RenderLoop {
             renderer,
             spectrum_buffer,
             mode,
+            audio_input,
+            device_picker: None,
+            preprocess_status: None,
+            window_control: None,
+            weighting: None,
+            overrun_reporter: OverrunReporter::new(Instant::now()),
             target_fps,
+            running,
         }
     }
     
+    /// Show gain, AGC and DC filter state in the HUD
+    pub fn with_preprocess_status(mut self, status: Arc<PreprocessStatus>) -> Self {
+        self.preprocess_status = Some(status);
+        self
+    }
+    
+    /// Cycle the FFT window with 'w' and show the active window in the HUD
+    pub fn with_window_control(mut self, control: Arc<WindowControl>) -> Self {
+        self.window_control = Some(control);
+        self
+    }
+    
+    /// Show the frequency weighting in the HUD
+    pub fn with_weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = Some(weighting);
+        self
+    }
+    
     /// Run the main rendering loop
     /// Returns when user presses 'q' or Ctrl+C
     pub fn run(&mut self) -> io::Result<()> {
@@ -424,7 +642,7 @@ impl RenderLoop {
         
         info!("Starting render loop");
         
-        loop {
+        while self.running.load(Ordering::SeqCst) {
             let frame_start = Instant::now();
             
             // Check for resize
@@ -433,15 +651,35 @@ impl RenderLoop {
             // Check for user input (non-blocking)
             if event::poll(Duration::from_millis(0))? {
                 if let Event::Key(key_event) = event::read()? {
+                    let control = key_event.modifiers.contains(event::KeyModifiers::CONTROL);
+                    
                     match key_event.code {
+                        // The device picker captures navigation keys while it is open
+                        code if self.device_picker.is_some() && code != KeyCode::Char('q') && !control => {
+                            self.handle_picker_key(code);
+                        }
+                        KeyCode::Char('d') => {
+                            self.device_picker = Some(DevicePicker::new(
+                                self.audio_input.device_names(),
+                                self.audio_input.source().name(),
+                            ));
+                        }
                         KeyCode::Char('q') | KeyCode::Esc => {
                             info!("User requested exit");
                             break;
                         }
-                        KeyCode::Char('c') if key_event.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                        KeyCode::Char('c') if control => {
                             info!("Ctrl+C pressed");
                             break;
                         }
+                        KeyCode::Char('w') => {
+                            if let Some(ref control) = self.window_control {
+                                info!("Switched to {} window", control.cycle().name());
+                            }
+                        }
+                        code @ (KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right | KeyCode::Char('l')) => {
+                            self.handle_playback_key(code);
+                        }
                         _ => {}
                     }
                 }
@@ -452,14 +690,15 @@ impl RenderLoop {
                 Ok(data) => data.bands.clone(),
                 Err(e) => {
                     warn!("Failed to lock spectrum buffer: {}", e);
-                    vec![0.0; 32] // Fallback to empty spectrum
+                    vec![SILENCE_DB; 32] // Fallback to empty spectrum
                 }
             };
             
-            // Apply sensitivity scaling to spectrum values
+            // Apply sensitivity as an amplitude gain on the dBFS levels
+            let sensitivity_db = 20.0 * self.renderer.config.sensitivity.log10();
             let scaled_spectrum: Vec<f32> = spectrum
                 .iter()
-                .map(|&val| val * self.renderer.config.sensitivity)
+                .map(|&val| val + sensitivity_db)
                 .collect();
             
             // Clear canvas
@@ -471,6 +710,24 @@ impl RenderLoop {
             // Delegate rendering to active visualizer mode
             self.mode.render(&scaled_spectrum, self.renderer.canvas_mut(), &config);
             
+            // Let the audio source recover from device loss and report its state
+            if self.audio_input.source_mut().poll() == SourceStatus::Reconnecting {
+                let message = format!(" Reconnecting to {}... ", self.audio_input.source().name());
+                let canvas = self.renderer.canvas_mut();
+                let y = canvas.height() / 2;
+                canvas.draw_centered_text(y, &message, Color::Yellow);
+            }
+            
+            self.draw_playback_status();
+            
+            self.draw_hud();
+            
+            if let Some(ref picker) = self.device_picker {
+                picker.render(self.renderer.canvas_mut());
+            }
+            
+            self.report_overruns();
+            
             // Flush canvas to terminal display
             self.renderer.flush()?;
             
@@ -486,8 +743,140 @@ impl RenderLoop {
         Ok(())
     }
     
+    /// Draw preprocessing, window and weighting state on the top row
+    fn draw_hud(&mut self) {
+        let hud = hud_text(self.preprocess_status.as_deref(), self.window_control.as_deref(), self.weighting);
+        if let Some(hud) = hud {
+            self.renderer.canvas_mut().draw_text(0, 0, &hud, Color::DarkGrey);
+        }
+    }
+    
+    /// Log samples dropped by the audio source since the last report
+    /// The audio thread only counts overruns; logging happens here, at most once per second
+    fn report_overruns(&mut self) {
+        if let Some(dropped) = self.overrun_reporter.check(self.audio_input.overruns(), Instant::now()) {
+            warn!("Ring buffer overrun: dropped {} samples", dropped);
+        }
+    }
+    
+    /// Handle pause (space), seek (left/right) and loop ('l') for sources that support them
+    fn handle_playback_key(&mut self, code: KeyCode) {
+        let Some(playback) = self.audio_input.source().playback() else {
+            return;
+        };
+        
+        match code {
+            KeyCode::Char(' ') => playback.toggle_pause(),
+            KeyCode::Left => playback.seek_by(-SEEK_STEP_SECONDS),
+            KeyCode::Right => playback.seek_by(SEEK_STEP_SECONDS),
+            KeyCode::Char('l') => playback.toggle_loop(),
+            _ => {}
+        }
+    }
+    
+    /// Draw the transport state (e.g. "▶ 1:05 / 3:42  loop") on the bottom row
+    fn draw_playback_status(&mut self) {
+        let Some(playback) = self.audio_input.source().playback() else {
+            return;
+        };
+        
+        let state = if playback.is_finished() {
+            "■"
+        } else if playback.is_paused() {
+            "⏸"
+        } else {
+            "▶"
+        };
+        let mut status = format!(" {} {}", state, format_time(playback.position_secs()));
+        if let Some(duration) = playback.duration_secs() {
+            status.push_str(&format!(" / {}", format_time(duration)));
+        }
+        if playback.is_looping() {
+            status.push_str("  loop");
+        }
+        status.push(' ');
+        
+        let canvas = self.renderer.canvas_mut();
+        let y = canvas.height().saturating_sub(1);
+        canvas.draw_text(0, y, &status, Color::White);
+    }
+    
+    /// Handle a key press while the device picker is open
+    fn handle_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = self.device_picker.as_mut() else {
+            return;
+        };
+        
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => picker.move_selection(false),
+            KeyCode::Down | KeyCode::Char('j') => picker.move_selection(true),
+            KeyCode::Esc | KeyCode::Char('d') => self.device_picker = None,
+            KeyCode::Enter => {
+                let Some(device) = picker.selected_device().map(str::to_string) else {
+                    return;
+                };
+                match self.audio_input.switch_to_device(&device) {
+                    Ok(()) => self.device_picker = None,
+                    Err(e) => {
+                        warn!("Failed to switch to device '{}': {}", device, e);
+                        picker.message = Some(format!("Failed to switch: {}", e));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    
     /// Get a mutable reference to the renderer for cleanup
     pub fn renderer_mut(&mut self) -> &mut TerminalRenderer {
         &mut self.renderer
     }
+    
+    /// Get a mutable reference to the audio input for cleanup
+    pub fn audio_input_mut(&mut self) -> &mut AudioInput {
+        &mut self.audio_input
+    }
+}
+
+/// Format seconds as minutes and seconds (e.g. "3:07")
+fn format_time(seconds: f64) -> String {
+    let whole = seconds.max(0.0) as u64;
+    format!("{}:{:02}", whole / 60, whole % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocess::{PreprocessConfig, Preprocessor};
+    use crate::window::WindowKind;
+
+    #[test]
+    fn test_hud_text_lists_active_stages() {
+        assert_eq!(hud_text(None, None, None), None);
+
+        let preprocessor = Preprocessor::new(PreprocessConfig { gain_db: 6.0, agc: None, dc_cutoff_hz: Some(10.0) }, 48000);
+        let window = WindowControl::new(WindowKind::Kaiser, 8.6);
+        assert_eq!(
+            hud_text(Some(&preprocessor.status()), Some(&window), Some(Weighting::A)).as_deref(),
+            Some(" gain +6.0 dB | DC 10 Hz | window kaiser β=8.6 | A-weighted ")
+        );
+        assert_eq!(hud_text(None, Some(&window), None).as_deref(), Some(" window kaiser β=8.6 "));
+        assert_eq!(hud_text(None, None, Some(Weighting::Z)).as_deref(), Some(" Z-weighted "));
+    }
+
+    #[test]
+    fn test_overrun_reports_are_rate_limited() {
+        let start = Instant::now();
+        let mut reporter = OverrunReporter::new(start);
+
+        // Nothing is reported before the interval elapses
+        assert_eq!(reporter.check(10, start + Duration::from_millis(500)), None);
+        // Then the whole backlog is reported once
+        let first = start + OVERRUN_REPORT_INTERVAL;
+        assert_eq!(reporter.check(10, first), Some(10));
+        // Only new drops are reported, and no report without new drops
+        assert_eq!(reporter.check(25, first + Duration::from_millis(200)), None);
+        assert_eq!(reporter.check(25, first + OVERRUN_REPORT_INTERVAL), Some(15));
+        assert_eq!(reporter.check(25, first + 2 * OVERRUN_REPORT_INTERVAL), None);
+    }
 }
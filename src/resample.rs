@@ -0,0 +1,169 @@
+// Streaming sample rate conversion to the internal analysis rate
+
+use std::f64::consts::PI;
+
+use crate::audio::RING_CHANNELS;
+
+/// Filter length in input samples
+const TAPS: usize = 32;
+
+/// Half the filter length; also the resampler latency in input samples
+const HALF_TAPS: usize = TAPS / 2;
+
+/// Number of precomputed fractional positions between input samples
+const PHASES: usize = 256;
+
+/// Fraction of the output Nyquist frequency kept by the anti-aliasing filter
+const CUTOFF: f64 = 0.95;
+
+/// Polyphase windowed-sinc resampler for interleaved stereo frames
+///
+/// Works one frame at a time with fixed-size state, so it can run inside the
+/// audio callback without allocating.
+pub struct Resampler {
+    /// Input samples advanced per output sample (input rate / output rate)
+    step: f64,
+    /// Time of the next output frame in input samples, relative to the newest input
+    next_time: f64,
+    /// Filter coefficients for each phase, `PHASES + 1` rows of `TAPS`
+    table: Vec<f32>,
+    /// Per-channel input history, written twice so the last `TAPS` samples are contiguous
+    history: [Vec<f32>; RING_CHANNELS],
+    position: usize,
+}
+
+impl Resampler {
+    /// Create a resampler from `input_rate` to `output_rate`
+    /// Returns None when the rates match and no conversion is needed
+    pub fn new(input_rate: u32, output_rate: u32) -> Option<Self> {
+        if input_rate == output_rate || input_rate == 0 || output_rate == 0 {
+            return None;
+        }
+
+        let step = input_rate as f64 / output_rate as f64;
+        // Downsampling lowers the cutoff below the output Nyquist frequency
+        let cutoff = CUTOFF * (1.0 / step).min(1.0);
+
+        Some(Resampler {
+            step,
+            next_time: 0.0,
+            table: Self::build_table(cutoff),
+            history: std::array::from_fn(|_| vec![0.0; TAPS * 2]),
+            position: 0,
+        })
+    }
+
+    /// Coefficients h(phase - j) for every phase, Blackman-windowed and normalized to unity DC gain
+    fn build_table(cutoff: f64) -> Vec<f32> {
+        let mut table = Vec::with_capacity((PHASES + 1) * TAPS);
+
+        for phase in 0..=PHASES {
+            let fraction = phase as f64 / PHASES as f64;
+            let row: Vec<f64> = (0..TAPS)
+                .map(|tap| {
+                    let offset = tap as f64 - HALF_TAPS as f64 + 1.0;
+                    let x = fraction - offset;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        (PI * cutoff * x).sin() / (PI * cutoff * x)
+                    };
+                    // Blackman window spanning the full filter length
+                    let n = (x + HALF_TAPS as f64) / TAPS as f64;
+                    let window = 0.42 - 0.5 * (2.0 * PI * n).cos() + 0.08 * (4.0 * PI * n).cos();
+                    sinc * window.max(0.0)
+                })
+                .collect();
+
+            let sum: f64 = row.iter().sum();
+            table.extend(row.iter().map(|c| (c / sum) as f32));
+        }
+
+        table
+    }
+
+    /// Feed one input frame and emit every output frame that becomes available
+    pub fn process(&mut self, left: f32, right: f32, mut emit: impl FnMut(f32, f32)) {
+        for (history, sample) in self.history.iter_mut().zip([left, right]) {
+            history[self.position] = sample;
+            history[self.position + TAPS] = sample;
+        }
+        self.position = (self.position + 1) % TAPS;
+        self.next_time -= 1.0;
+
+        // An output at time t needs inputs up to floor(t) + HALF_TAPS, so it is
+        // due once t falls into the window ending at the newest input
+        while self.next_time < 1.0 - HALF_TAPS as f64 {
+            let fraction = self.next_time + HALF_TAPS as f64;
+            let phase = fraction * PHASES as f64;
+            let row = (phase as usize).min(PHASES - 1);
+            let blend = (phase - row as f64) as f32;
+
+            let low = &self.table[row * TAPS..(row + 1) * TAPS];
+            let high = &self.table[(row + 1) * TAPS..(row + 2) * TAPS];
+
+            let mut output = [0.0f32; RING_CHANNELS];
+            for (channel, history) in self.history.iter().enumerate() {
+                // Oldest to newest input in the filter window
+                let window = &history[self.position..self.position + TAPS];
+                output[channel] = window
+                    .iter()
+                    .zip(low.iter().zip(high))
+                    .map(|(&x, (&a, &b))| x * (a + (b - a) * blend))
+                    .sum();
+            }
+
+            emit(output[0], output[1]);
+            self.next_time += self.step;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resample_sine(input_rate: u32, output_rate: u32, frequency: f64) -> Vec<f32> {
+        let mut resampler = Resampler::new(input_rate, output_rate).unwrap();
+        let mut output = Vec::new();
+        for n in 0..input_rate as usize {
+            let x = (2.0 * PI * frequency * n as f64 / input_rate as f64).sin() as f32;
+            resampler.process(x, x, |l, _| output.push(l));
+        }
+        output
+    }
+
+    #[test]
+    fn test_output_length_follows_ratio() {
+        assert!(Resampler::new(48000, 48000).is_none());
+        assert!(resample_sine(48000, 44100, 1000.0).len().abs_diff(44100) <= HALF_TAPS);
+        assert!(resample_sine(22050, 44100, 1000.0).len().abs_diff(44100) <= 2 * HALF_TAPS);
+    }
+
+    #[test]
+    fn test_sine_keeps_frequency_and_level() {
+        let output = resample_sine(96000, 44100, 1000.0);
+
+        // Skip the filter warm-up, then compare against an ideal 1 kHz sine at 44.1 kHz
+        // Output k sits at input time k * step - 1
+        let latency = 44100.0 / 96000.0;
+        let error = output[1000..40000]
+            .iter()
+            .enumerate()
+            .map(|(i, &y)| {
+                let t = (i + 1000) as f64 - latency;
+                let expected = (2.0 * PI * 1000.0 * t / 44100.0).sin() as f32;
+                (y - expected).abs()
+            })
+            .fold(0.0f32, f32::max);
+        assert!(error < 0.01, "max error {}", error);
+    }
+
+    #[test]
+    fn test_downsampling_suppresses_aliases() {
+        // 30 kHz is above the 22.05 kHz Nyquist frequency of the output
+        let output = resample_sine(96000, 44100, 30000.0);
+        let peak = output[1000..].iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak < 0.01, "alias peak {}", peak);
+    }
+}
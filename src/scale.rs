@@ -0,0 +1,197 @@
+// Frequency scales used to lay out the analyzer bands
+
+/// Default lowest analyzed frequency in Hz
+pub const DEFAULT_MIN_FREQ: f32 = 20.0;
+
+/// Default highest analyzed frequency in Hz
+pub const DEFAULT_MAX_FREQ: f32 = 20000.0;
+
+/// Reference frequency of the ISO/IEC octave band series
+const OCTAVE_REFERENCE_HZ: f64 = 1000.0;
+
+/// Base-10 octave ratio from IEC 61260-1
+const OCTAVE_RATIO: f64 = 1.9952623149688795; // 10^(3/10)
+
+/// Lower edge, upper edge and center of a band in Hz
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandLimits {
+    pub low: f32,
+    pub high: f32,
+    pub center: f32,
+}
+
+/// How band edges are spaced between the minimum and maximum frequency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrequencyScale {
+    Linear,
+    Log,
+    Mel,
+    Bark,
+    Erb,
+    /// ISO fractional-octave bands; the value is the fraction denominator (1, 3 or 6)
+    Octave(u32),
+}
+
+impl FrequencyScale {
+    /// Names accepted on the command line
+    pub const NAMES: [&'static str; 8] = ["linear", "log", "mel", "bark", "erb", "octave", "third-octave", "sixth-octave"];
+
+    /// Parse a scale name; fractional octaves can also be written as 1/1, 1/3 and 1/6
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "linear" => Ok(FrequencyScale::Linear),
+            "log" => Ok(FrequencyScale::Log),
+            "mel" => Ok(FrequencyScale::Mel),
+            "bark" => Ok(FrequencyScale::Bark),
+            "erb" => Ok(FrequencyScale::Erb),
+            "octave" | "1/1" => Ok(FrequencyScale::Octave(1)),
+            "third-octave" | "1/3" => Ok(FrequencyScale::Octave(3)),
+            "sixth-octave" | "1/6" => Ok(FrequencyScale::Octave(6)),
+            _ => Err(format!(
+                "Invalid frequency scale '{}'. Valid scales are: {}",
+                name,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+
+    /// Display name of the scale
+    pub fn name(self) -> String {
+        match self {
+            FrequencyScale::Linear => "linear".to_string(),
+            FrequencyScale::Log => "log".to_string(),
+            FrequencyScale::Mel => "mel".to_string(),
+            FrequencyScale::Bark => "Bark".to_string(),
+            FrequencyScale::Erb => "ERB".to_string(),
+            FrequencyScale::Octave(fraction) => format!("1/{} octave", fraction),
+        }
+    }
+
+    /// Whether the band count is set by the standard rather than by the caller
+    pub fn has_fixed_bands(self) -> bool {
+        matches!(self, FrequencyScale::Octave(_))
+    }
+
+    /// Compute the bands covering `min_freq` to `max_freq`
+    /// Fractional-octave scales return every standard band overlapping the range
+    /// and ignore `num_bands`; the other scales split the range into `num_bands`
+    /// bands of equal width on the scale
+    pub fn bands(self, num_bands: usize, min_freq: f32, max_freq: f32) -> Vec<BandLimits> {
+        if let FrequencyScale::Octave(fraction) = self {
+            return octave_bands(fraction, min_freq as f64, max_freq as f64);
+        }
+
+        let low = self.hz_to_scale(min_freq as f64);
+        let high = self.hz_to_scale(max_freq as f64);
+        let step = (high - low) / num_bands.max(1) as f64;
+
+        (0..num_bands)
+            .map(|i| {
+                let start = low + step * i as f64;
+                BandLimits {
+                    low: self.scale_to_hz(start) as f32,
+                    high: self.scale_to_hz(start + step) as f32,
+                    center: self.scale_to_hz(start + step / 2.0) as f32,
+                }
+            })
+            .collect()
+    }
+
+    /// Convert a frequency in Hz to a position on the scale
+    fn hz_to_scale(self, hz: f64) -> f64 {
+        match self {
+            FrequencyScale::Linear => hz,
+            FrequencyScale::Log | FrequencyScale::Octave(_) => hz.ln(),
+            // O'Shaughnessy mel scale
+            FrequencyScale::Mel => 2595.0 * (1.0 + hz / 700.0).log10(),
+            // Traunmüller's Bark approximation
+            FrequencyScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53,
+            // Glasberg & Moore ERB-rate scale
+            FrequencyScale::Erb => 21.4 * (1.0 + 0.00437 * hz).log10(),
+        }
+    }
+
+    /// Convert a position on the scale back to Hz
+    fn scale_to_hz(self, value: f64) -> f64 {
+        match self {
+            FrequencyScale::Linear => value,
+            FrequencyScale::Log | FrequencyScale::Octave(_) => value.exp(),
+            FrequencyScale::Mel => 700.0 * (10f64.powf(value / 2595.0) - 1.0),
+            FrequencyScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+            FrequencyScale::Erb => (10f64.powf(value / 21.4) - 1.0) / 0.00437,
+        }
+    }
+}
+
+/// Standard 1/b-octave bands (IEC 61260-1, base 10) overlapping `min_freq` to `max_freq`
+/// Mid-band frequencies are G^(x/b) * 1 kHz for odd b and G^((2x+1)/(2b)) * 1 kHz for even b
+fn octave_bands(fraction: u32, min_freq: f64, max_freq: f64) -> Vec<BandLimits> {
+    let b = fraction.max(1) as f64;
+    let center = |x: i32| {
+        let exponent = if fraction % 2 == 1 {
+            x as f64 / b
+        } else {
+            (2.0 * x as f64 + 1.0) / (2.0 * b)
+        };
+        OCTAVE_REFERENCE_HZ * OCTAVE_RATIO.powf(exponent)
+    };
+    let half_band = OCTAVE_RATIO.powf(1.0 / (2.0 * b));
+
+    // Band index whose center is nearest the minimum frequency, minus one to be safe
+    let mut x = ((min_freq / OCTAVE_REFERENCE_HZ).log(OCTAVE_RATIO) * b).floor() as i32 - 1;
+    let mut bands = Vec::new();
+    loop {
+        let fm = center(x);
+        let (low, high) = (fm / half_band, fm * half_band);
+        if low >= max_freq {
+            break;
+        }
+        if high > min_freq {
+            bands.push(BandLimits {
+                low: low as f32,
+                high: high as f32,
+                center: fm as f32,
+            });
+        }
+        x += 1;
+    }
+    bands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_third_octave_bands_match_iso_centers() {
+        let bands = FrequencyScale::Octave(3).bands(0, 20.0, 20000.0);
+        assert_eq!(bands.len(), 31);
+
+        // Exact mid-band frequencies round to the nominal 20, 1000 and 20000 Hz
+        assert!((bands[0].center - 19.95).abs() < 0.01);
+        assert!((bands[17].center - 1000.0).abs() < 1e-3);
+        assert!((bands[30].center - 19952.6).abs() < 0.1);
+
+        // Adjacent bands share their edges
+        assert!((bands[0].high - bands[1].low).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_sixth_octave_centers_straddle_reference() {
+        let bands = FrequencyScale::Octave(6).bands(0, 900.0, 1100.0);
+        let centers: Vec<f32> = bands.iter().map(|b| b.center).collect();
+        assert!(centers.iter().any(|&c| (c - 1059.3).abs() < 0.1), "{:?}", centers);
+        assert!(centers.iter().any(|&c| (c - 944.1).abs() < 0.1), "{:?}", centers);
+    }
+
+    #[test]
+    fn test_perceptual_scales_round_trip_and_cover_range() {
+        for scale in [FrequencyScale::Linear, FrequencyScale::Log, FrequencyScale::Mel, FrequencyScale::Bark, FrequencyScale::Erb] {
+            let bands = scale.bands(40, 50.0, 16000.0);
+            assert_eq!(bands.len(), 40);
+            assert!((bands[0].low - 50.0).abs() < 0.01, "{:?} starts at {}", scale, bands[0].low);
+            assert!((bands[39].high - 16000.0).abs() < 1.0, "{:?} ends at {}", scale, bands[39].high);
+            assert!(bands.windows(2).all(|pair| pair[0].high <= pair[1].high));
+        }
+    }
+}
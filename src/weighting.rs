@@ -0,0 +1,108 @@
+// Frequency weighting curves applied to the power spectrum before banding
+
+/// Frequency weighting of the analyzed spectrum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    /// Flat response (no weighting)
+    Z,
+    /// IEC 61672 A-weighting, as used by most SPL meters
+    A,
+    /// IEC 61672 C-weighting, for peak and low-frequency levels
+    C,
+    /// ITU-R BS.468 noise weighting
+    Itu468,
+}
+
+impl Weighting {
+    /// Parse a weighting name as given on the command line
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "z" | "none" => Ok(Weighting::Z),
+            "a" => Ok(Weighting::A),
+            "c" => Ok(Weighting::C),
+            "itu-r-468" | "468" => Ok(Weighting::Itu468),
+            _ => Err(format!(
+                "Invalid weighting '{}'. Valid weightings are: z, a, c, itu-r-468",
+                name
+            )),
+        }
+    }
+
+    /// Display name of the weighting
+    pub fn name(self) -> &'static str {
+        match self {
+            Weighting::Z => "Z",
+            Weighting::A => "A",
+            Weighting::C => "C",
+            Weighting::Itu468 => "ITU-R 468",
+        }
+    }
+
+    /// Gain of the curve in dB at `freq` Hz; every curve reads 0 dB at 1 kHz
+    pub fn gain_db(self, freq: f64) -> f64 {
+        let f2 = freq * freq;
+        match self {
+            Weighting::Z => 0.0,
+            Weighting::A => {
+                let response = 12194.0f64.powi(2) * f2 * f2
+                    / ((f2 + 20.6f64.powi(2))
+                        * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt()
+                        * (f2 + 12194.0f64.powi(2)));
+                20.0 * response.log10() + 2.0
+            }
+            Weighting::C => {
+                let response = 12194.0f64.powi(2) * f2 / ((f2 + 20.6f64.powi(2)) * (f2 + 12194.0f64.powi(2)));
+                20.0 * response.log10() + 0.062
+            }
+            Weighting::Itu468 => {
+                let h1 = -4.737338981378384e-24 * f2.powi(3) + 2.043828333606125e-15 * f2 * f2
+                    - 1.363894795463638e-7 * f2
+                    + 1.0;
+                let h2 = 1.306612257412824e-19 * freq.powi(5) - 2.118150887518656e-11 * freq.powi(3)
+                    + 5.559488023498642e-4 * freq;
+                let response = 1.246332637532143e-4 * freq / (h1 * h1 + h2 * h2).sqrt();
+                18.2 + 20.0 * response.log10()
+            }
+        }
+    }
+
+    /// Linear power gain for each FFT bin from DC to Nyquist
+    /// Bin frequency = (bin_index * sample_rate) / fft_size
+    pub fn power_gains(self, fft_size: usize, sample_rate: f32) -> Vec<f32> {
+        let bin_width = sample_rate as f64 / fft_size as f64;
+        (0..=fft_size / 2)
+            .map(|k| match self {
+                Weighting::Z => 1.0,
+                // The weighted curves reject DC entirely
+                _ if k == 0 => 0.0,
+                _ => 10f64.powf(self.gain_db(k as f64 * bin_width) / 10.0) as f32,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves_match_standard_tables() {
+        // (weighting, frequency, tabulated gain in dB)
+        let table = [
+            (Weighting::A, 100.0, -19.1),
+            (Weighting::A, 1000.0, 0.0),
+            (Weighting::A, 10000.0, -2.5),
+            (Weighting::C, 31.5, -3.0),
+            (Weighting::C, 8000.0, -3.0),
+            (Weighting::Itu468, 100.0, -19.8),
+            (Weighting::Itu468, 1000.0, 0.0),
+            (Weighting::Itu468, 6300.0, 12.2),
+            (Weighting::Itu468, 20000.0, -22.2),
+        ];
+        for (weighting, freq, expected) in table {
+            let gain = weighting.gain_db(freq);
+            assert!((gain - expected).abs() < 0.1, "{} at {} Hz: {:.2} dB", weighting.name(), freq, gain);
+        }
+        assert_eq!(Weighting::Z.power_gains(16, 16.0), vec![1.0; 9]);
+    }
+}
@@ -0,0 +1,207 @@
+// Window functions applied to each block before the FFT
+
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default Kaiser shape parameter (sidelobes comparable to Blackman)
+pub const DEFAULT_KAISER_BETA: f32 = 8.6;
+
+/// Largest accepted Kaiser shape parameter
+pub const MAX_KAISER_BETA: f32 = 50.0;
+
+/// Window function used to taper each FFT block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowKind {
+    Hann,
+    Hamming,
+    Blackman,
+    BlackmanHarris,
+    FlatTop,
+    Kaiser,
+    Rectangular,
+}
+
+impl WindowKind {
+    /// All windows, in the order the runtime toggle cycles through them
+    pub const ALL: [WindowKind; 7] = [
+        WindowKind::Hann,
+        WindowKind::Hamming,
+        WindowKind::Blackman,
+        WindowKind::BlackmanHarris,
+        WindowKind::FlatTop,
+        WindowKind::Kaiser,
+        WindowKind::Rectangular,
+    ];
+
+    /// Parse a window name as given on the command line
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == name.to_lowercase())
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|kind| kind.name()).collect();
+                format!("Invalid window '{}'. Valid windows are: {}", name, names.join(", "))
+            })
+    }
+
+    /// Command line name of the window
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowKind::Hann => "hann",
+            WindowKind::Hamming => "hamming",
+            WindowKind::Blackman => "blackman",
+            WindowKind::BlackmanHarris => "blackman-harris",
+            WindowKind::FlatTop => "flat-top",
+            WindowKind::Kaiser => "kaiser",
+            WindowKind::Rectangular => "rectangular",
+        }
+    }
+
+    /// Generate `size` symmetric window coefficients
+    /// `kaiser_beta` is only used by the Kaiser window
+    pub fn generate(self, size: usize, kaiser_beta: f32) -> Vec<f32> {
+        let coefficients: &[f64] = match self {
+            WindowKind::Hann => &[0.5, 0.5],
+            WindowKind::Hamming => &[0.54, 0.46],
+            WindowKind::Blackman => &[0.42, 0.5, 0.08],
+            WindowKind::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            WindowKind::FlatTop => &[0.21557895, 0.41663158, 0.277263158, 0.083578947, 0.006947368],
+            WindowKind::Kaiser => return kaiser_window(size, kaiser_beta as f64),
+            WindowKind::Rectangular => return vec![1.0; size],
+        };
+        cosine_sum_window(size, coefficients)
+    }
+}
+
+/// Window selection shared between the FFT thread and the render loop
+pub struct WindowControl {
+    /// Index into `WindowKind::ALL`
+    index: AtomicUsize,
+    kaiser_beta: f32,
+}
+
+impl WindowControl {
+    pub fn new(kind: WindowKind, kaiser_beta: f32) -> Self {
+        let index = WindowKind::ALL.iter().position(|k| *k == kind).unwrap_or(0);
+        WindowControl {
+            index: AtomicUsize::new(index),
+            kaiser_beta,
+        }
+    }
+
+    /// The currently selected window
+    pub fn current(&self) -> WindowKind {
+        WindowKind::ALL[self.index.load(Ordering::Relaxed) % WindowKind::ALL.len()]
+    }
+
+    /// Switch to the next window and return it
+    pub fn cycle(&self) -> WindowKind {
+        let next = (self.index.load(Ordering::Relaxed) + 1) % WindowKind::ALL.len();
+        self.index.store(next, Ordering::Relaxed);
+        WindowKind::ALL[next]
+    }
+
+    pub fn kaiser_beta(&self) -> f32 {
+        self.kaiser_beta
+    }
+
+    /// Short description for the HUD, e.g. "kaiser β=8.6"
+    pub fn hud_text(&self) -> String {
+        match self.current() {
+            WindowKind::Kaiser => format!("kaiser β={}", self.kaiser_beta),
+            kind => kind.name().to_string(),
+        }
+    }
+}
+
+/// Mean of the window coefficients, i.e. its gain for a bin-centered sinusoid
+pub fn coherent_gain(window: &[f32]) -> f32 {
+    if window.is_empty() {
+        return 1.0;
+    }
+    window.iter().sum::<f32>() / window.len() as f32
+}
+
+/// Equivalent noise bandwidth in bins: N * Σw² / (Σw)²
+/// Summed bin power of a sinusoid exceeds its peak bin power by this factor
+pub fn equivalent_noise_bandwidth(window: &[f32]) -> f32 {
+    let sum: f32 = window.iter().sum();
+    let sum_squares: f32 = window.iter().map(|w| w * w).sum();
+    if sum == 0.0 {
+        return 1.0;
+    }
+    window.len() as f32 * sum_squares / (sum * sum)
+}
+
+/// Generalized cosine window
+/// Formula: w(n) = Σ (-1)^k * a_k * cos(2πkn/(N-1))
+fn cosine_sum_window(size: usize, coefficients: &[f64]) -> Vec<f32> {
+    let denominator = (size.max(2) - 1) as f64;
+    (0..size)
+        .map(|n| {
+            let phase = 2.0 * PI * n as f64 / denominator;
+            coefficients
+                .iter()
+                .enumerate()
+                .map(|(k, &a)| {
+                    let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                    sign * a * (k as f64 * phase).cos()
+                })
+                .sum::<f64>() as f32
+        })
+        .collect()
+}
+
+/// Kaiser window
+/// Formula: w(n) = I0(β * sqrt(1 - (2n/(N-1) - 1)²)) / I0(β)
+fn kaiser_window(size: usize, beta: f64) -> Vec<f32> {
+    let denominator = (size.max(2) - 1) as f64;
+    let scale = bessel_i0(beta);
+    (0..size)
+        .map(|n| {
+            let x = 2.0 * n as f64 / denominator - 1.0;
+            (bessel_i0(beta * (1.0 - x * x).max(0.0).sqrt()) / scale) as f32
+        })
+        .collect()
+}
+
+/// Zeroth-order modified Bessel function of the first kind, by power series
+fn bessel_i0(x: f64) -> f64 {
+    let quarter_x_squared = x * x / 4.0;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 1..100 {
+        term *= quarter_x_squared / (k * k) as f64;
+        sum += term;
+        if term < sum * 1e-12 {
+            break;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coherent_gains() {
+        let gain = |kind: WindowKind| coherent_gain(&kind.generate(4096, DEFAULT_KAISER_BETA));
+        assert!((gain(WindowKind::Hann) - 0.5).abs() < 1e-3);
+        assert!((gain(WindowKind::Hamming) - 0.54).abs() < 1e-3);
+        assert!((gain(WindowKind::Blackman) - 0.42).abs() < 1e-3);
+        assert!((gain(WindowKind::FlatTop) - 0.2156).abs() < 1e-3);
+        assert_eq!(gain(WindowKind::Rectangular), 1.0);
+        assert!((equivalent_noise_bandwidth(&WindowKind::Hann.generate(4096, 0.0)) - 1.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_kaiser_shape() {
+        let window = WindowKind::Kaiser.generate(1025, DEFAULT_KAISER_BETA);
+        assert!((window[512] - 1.0).abs() < 1e-6);
+        assert!(window[0] < 0.01);
+        // A zero beta degenerates to a rectangular window
+        assert!(WindowKind::Kaiser.generate(64, 0.0).iter().all(|&w| (w - 1.0).abs() < 1e-6));
+    }
+}